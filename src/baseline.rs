@@ -0,0 +1,161 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::BufReader,
+    path::Path,
+};
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    error::RLinksError,
+    outcome::CheckResult,
+    text::{print_diagnostic, ColorsExt},
+};
+
+/// The only parts of a saved json report we need to compare two runs
+#[derive(Deserialize)]
+struct SavedReport {
+    links: Vec<SavedLink>,
+}
+#[derive(Deserialize)]
+struct SavedLink {
+    url: Url,
+    broken: bool,
+}
+
+/// How a link changed since the baseline run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    NewlyBroken,
+    StillBroken,
+    Fixed,
+    New,
+    Removed,
+    Unchanged,
+}
+impl Change {
+    pub fn label(self) -> &'static str {
+        match self {
+            Change::NewlyBroken => "newly broken",
+            Change::StillBroken => "still broken",
+            Change::Fixed => "fixed",
+            Change::New => "new",
+            Change::Removed => "removed",
+            Change::Unchanged => "unchanged",
+        }
+    }
+}
+
+/// Maps every url in a saved report to whether it was broken
+pub fn load_baseline(path: &Path) -> Result<HashMap<Url, bool>, RLinksError> {
+    let report: SavedReport = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    Ok(report
+        .links
+        .into_iter()
+        .map(|link| (link.url, link.broken))
+        .collect())
+}
+
+/// Classifies every link that appears in either run. A link that did not exist before and is
+/// broken now counts as newly broken
+pub fn compare(baseline: &HashMap<Url, bool>, results: &[CheckResult]) -> BTreeMap<Url, Change> {
+    let mut changes: BTreeMap<Url, Change> = results
+        .iter()
+        .map(|result| {
+            let broken = result.outcome.is_broken();
+            let previous = baseline.get(&result.url);
+            let change = match (previous, broken) {
+                // We couldn't check the link this time, so we assume it didn't change
+                (Some(true), _) if result.outcome.is_unchecked() => Change::StillBroken,
+                (None, _) if result.outcome.is_unchecked() => Change::New,
+                (Some(true), true) => Change::StillBroken,
+                (Some(true), false) => Change::Fixed,
+                (Some(false), false) => Change::Unchanged,
+                (None, false) => Change::New,
+                (Some(false), true) | (None, true) => Change::NewlyBroken,
+            };
+            (result.url.clone(), change)
+        })
+        .collect();
+    baseline.keys().for_each(|url| {
+        changes.entry(url.clone()).or_insert(Change::Removed);
+    });
+    changes
+}
+
+pub fn count(changes: &BTreeMap<Url, Change>, change: Change) -> usize {
+    changes.values().filter(|c| **c == change).count()
+}
+
+pub fn print_changes(changes: &BTreeMap<Url, Change>) {
+    for (url, change) in changes {
+        match change {
+            Change::NewlyBroken => format!("Newly broken: {}", url).print_in_red(),
+            Change::Fixed => format!("Fixed: {}", url).print_in_green(),
+            _ => (),
+        }
+    }
+    let summary: Vec<String> = [
+        Change::NewlyBroken,
+        Change::StillBroken,
+        Change::Fixed,
+        Change::New,
+        Change::Removed,
+    ]
+    .iter()
+    .map(|change| format!("{} {}", count(changes, *change), change.label()))
+    .collect();
+    print_diagnostic(&format!("Compared to baseline: {}", summary.join(", ")));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use http::StatusCode;
+    use url::Url;
+
+    use crate::{
+        baseline::{compare, Change},
+        outcome::{CheckResult, Outcome},
+        test_support,
+    };
+
+    fn result(url: &str, outcome: Outcome) -> CheckResult {
+        test_support::result(url, StatusCode::OK, outcome)
+    }
+
+    #[test]
+    fn test_compare() {
+        let baseline: HashMap<Url, bool> = vec![
+            ("https://a.com/", true),
+            ("https://b.com/", false),
+            ("https://c.com/", true),
+            ("https://gone.com/", false),
+        ]
+        .into_iter()
+        .map(|(url, broken)| (Url::parse(url).unwrap(), broken))
+        .collect();
+        let results = vec![
+            result("https://a.com/", Outcome::HttpError),
+            result("https://b.com/", Outcome::Timeout),
+            result("https://c.com/", Outcome::Ok),
+            result("https://d.com/", Outcome::Ok),
+            result("https://e.com/", Outcome::HttpError),
+        ];
+        let changes: Vec<Change> = compare(&baseline, &results).into_values().collect();
+        assert_eq!(
+            changes,
+            vec![
+                Change::StillBroken,
+                Change::NewlyBroken,
+                Change::Fixed,
+                Change::New,
+                Change::NewlyBroken,
+                Change::Removed
+            ]
+        );
+    }
+}
@@ -1,33 +1,113 @@
 use clap::{App, AppSettings, Arg, SubCommand};
 
-use crate::error::RLinksError;
+use crate::{
+    error::RLinksError,
+    headers::ScopedHeaders,
+    json_links::JsonSelector,
+    local::is_local_path,
+    proxy::ProxyConfig,
+    resource::parse_size,
+    status::StatusPolicy,
+    tls::{extend_system_bundle, parse_window, TlsConfig},
+    url_fix::add_http,
+};
 use regex::Regex;
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 const TIMEOUT_SECONDS: u64 = 10;
 const DEFAULT_PAR_REQ: usize = 2;
+const DEFAULT_CERT_EXPIRY_WARN: &str = "30d";
 const RLINKS_USER_AGENT: &str =
     "Mozilla/5.0 (compatible; Rlinks/0.5; +https://github.com/jlricon/rlinks/)";
+arg_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum OutputFormat {
+        Text,
+        Html,
+        Csv,
+        Tsv,
+        Json,
+    }
+}
+arg_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum DumpFormat {
+        Txt,
+        Csv,
+        Tsv,
+        Json,
+    }
+}
 #[derive(Debug)]
 pub enum CommandConfig {
     Base(BaseConfig),
     Dump(DumpConfig),
 }
+/// Settings that affect every request we make
+#[derive(Debug)]
+pub struct RequestConfig {
+    pub user_agent: String,
+    pub headers: ScopedHeaders,
+    pub proxy: ProxyConfig,
+    pub tls: TlsConfig,
+    pub status: StatusPolicy,
+    /// Images, scripts and stylesheets larger than this many bytes are flagged
+    pub max_resource_size: Option<u64>,
+    /// Look up the domains of mailto addresses
+    pub check_mail_domains: bool,
+}
+/// Settings used to build the http client
+#[derive(Debug)]
+pub struct ClientConfig {
+    pub timeout: u64,
+    /// Netscape cookies.txt file to load cookies from
+    pub cookies: Option<String>,
+    /// Where to save the cookies once we are done
+    pub save_cookies: Option<String>,
+}
 #[derive(Debug)]
 pub struct BaseConfig {
     pub n_par: usize,
-    pub user_agent: String,
+    pub request: RequestConfig,
     pub show_ok: bool,
-    pub timeout: u64,
+    pub client: ClientConfig,
     pub url: String,
     pub ignore_urls: Option<Regex>,
+    /// Only look for links in these parts of json documents
+    pub json_selector: Option<JsonSelector>,
+    /// Command that prints the html of a page after its scripts run
+    pub render_command: Option<String>,
+    pub format: OutputFormat,
+    pub output_file: Option<String>,
+    pub watch: bool,
+    pub baseline: Option<String>,
+    pub fail_on_new: bool,
+    /// Warn about certificates expiring within this window
+    pub cert_expiry_warn: Duration,
+    pub soft_404: bool,
+    /// Ask for mixed content over https, to tell if it can be upgraded
+    pub probe_https: bool,
+    /// Look for https replacements of working http links
+    pub suggest_https: bool,
 }
 #[derive(Debug)]
 pub struct DumpConfig {
     pub url: String,
-    pub user_agent: String,
-    pub timeout: u64,
+    pub n_par: usize,
+    pub request: RequestConfig,
+    pub client: ClientConfig,
     pub output_file: String,
     pub ignore_urls: Option<Regex>,
+    /// Only look for links in these parts of json documents
+    pub json_selector: Option<JsonSelector>,
+    /// Command that prints the html of a page after its scripts run
+    pub render_command: Option<String>,
+    pub format: DumpFormat,
 }
 
 pub fn get_config(app: App) -> Result<CommandConfig, RLinksError> {
@@ -37,40 +117,235 @@ pub fn get_config(app: App) -> Result<CommandConfig, RLinksError> {
         .value_of("ignore_urls")
         .map(|v| Regex::new(v).unwrap());
     let url = value_t!(subcommand_matches.value_of("URL"), String)?;
+    let json_selector = subcommand_matches
+        .value_of("json_select")
+        .map(JsonSelector::parse)
+        .transpose()?;
+    let render_command = subcommand_matches
+        .value_of("render_command")
+        .map(str::to_owned);
     let timeout = subcommand_matches
         .value_of("timeout")
         .map_or_else(|| TIMEOUT_SECONDS, |val| val.parse().unwrap());
 
     let user_agent = subcommand_matches
         .value_of("user_agent")
-        .unwrap_or(&RLINKS_USER_AGENT)
+        .unwrap_or(RLINKS_USER_AGENT)
         .to_owned();
+    // Secrets are only sent to the site being checked unless told otherwise
+    let auth_hosts = match subcommand_matches.values_of("auth_host") {
+        Some(hosts) => hosts.map(str::to_owned).collect(),
+        None if is_local_path(&url) => vec![],
+        None => add_http(&url)?
+            .host_str()
+            .map(str::to_owned)
+            .into_iter()
+            .collect(),
+    };
+    let headers = ScopedHeaders::new(
+        subcommand_matches
+            .values_of("header")
+            .map_or_else(Vec::new, |values| values.collect()),
+        subcommand_matches.value_of("basic_auth"),
+        subcommand_matches.value_of("bearer_token"),
+        auth_hosts,
+    )?;
+    let proxy = ProxyConfig::new(
+        subcommand_matches.value_of("proxy"),
+        subcommand_matches
+            .values_of("no_proxy")
+            .map_or_else(Vec::new, |values| values.collect()),
+    )?;
+    let ca_cert = subcommand_matches.value_of("ca_cert").map(PathBuf::from);
+    // curl reports a bundle it can't read as a bad client certificate, on every https link
+    if let Some(path) = &ca_cert {
+        if File::open(path).is_err() {
+            return Err(RLinksError::LocalPathError(path.clone()));
+        }
+    }
+    let ca_bundle = ca_cert
+        .as_deref()
+        .map(extend_system_bundle)
+        .transpose()?
+        .map(Arc::new);
+    let tls = TlsConfig {
+        ca_cert,
+        ca_bundle,
+        client_cert: subcommand_matches.value_of("client_cert").map(|cert| {
+            (
+                PathBuf::from(cert),
+                subcommand_matches.value_of("client_key").map(PathBuf::from),
+            )
+        }),
+        insecure_hosts: subcommand_matches
+            .values_of("insecure")
+            .map_or_else(Vec::new, |hosts| hosts.map(str::to_owned).collect()),
+        diagnosed: Default::default(),
+    };
+    let status = StatusPolicy::new(
+        subcommand_matches.value_of("accept"),
+        subcommand_matches.value_of("get_fallback"),
+        subcommand_matches.value_of("config").map(Path::new),
+    )?;
+    let request = RequestConfig {
+        user_agent,
+        headers,
+        proxy,
+        tls,
+        status,
+        max_resource_size: subcommand_matches
+            .value_of("max_size")
+            .map(parse_size)
+            .transpose()?,
+        check_mail_domains: subcommand_matches.is_present("check_mail_domains"),
+    };
+    let client = ClientConfig {
+        timeout,
+        cookies: subcommand_matches.value_of("cookies").map(str::to_owned),
+        save_cookies: subcommand_matches
+            .value_of("save_cookies")
+            .map(str::to_owned),
+    };
+    let n_par = subcommand_matches
+        .value_of("n_par")
+        .map_or(DEFAULT_PAR_REQ, |v| v.parse().unwrap());
     match matches.subcommand_name().unwrap() {
         "dump" => Ok(CommandConfig::Dump(DumpConfig {
             url,
-            user_agent,
-            timeout,
+            n_par,
+            request,
+            client,
             output_file: value_t!(subcommand_matches.value_of("output"), String)?,
             ignore_urls,
+            json_selector,
+            render_command,
+            format: if subcommand_matches.is_present("format") {
+                value_t!(subcommand_matches.value_of("format"), DumpFormat)?
+            } else {
+                DumpFormat::Txt
+            },
         })),
         "check" => {
-            let n_par = subcommand_matches
-                .value_of("n_par")
-                .map_or(DEFAULT_PAR_REQ, |v| v.parse().unwrap());
+            let format = if subcommand_matches.is_present("format") {
+                value_t!(subcommand_matches.value_of("format"), OutputFormat)?
+            } else {
+                OutputFormat::Text
+            };
 
             Ok(CommandConfig::Base(BaseConfig {
                 n_par,
-                user_agent,
+                request,
                 show_ok: subcommand_matches.is_present("show_ok"),
-                timeout,
+                client,
                 url,
                 ignore_urls,
+                json_selector,
+                render_command,
+                format,
+                output_file: subcommand_matches.value_of("output").map(str::to_owned),
+                watch: subcommand_matches.is_present("watch"),
+                baseline: subcommand_matches.value_of("baseline").map(str::to_owned),
+                fail_on_new: subcommand_matches.is_present("fail_on_new"),
+                cert_expiry_warn: parse_window(
+                    subcommand_matches
+                        .value_of("cert_expiry_warn")
+                        .unwrap_or(DEFAULT_CERT_EXPIRY_WARN),
+                )?,
+                soft_404: subcommand_matches.is_present("soft_404"),
+                probe_https: subcommand_matches.is_present("probe_https"),
+                suggest_https: subcommand_matches.is_present("suggest_https"),
             }))
         }
         _ => unreachable!(),
     }
 }
 
+/// Arguments shared by every subcommand that makes requests
+fn request_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("n_par")
+            .short("p")
+            .long("n_par")
+            .help("Number of parallel requests per domain")
+            .takes_value(true),
+        Arg::with_name("header")
+            .short("H")
+            .long("header")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Extra 'Name: value' header. Use 'Name: env:VAR' to read the value from VAR"),
+        Arg::with_name("basic_auth")
+            .long("basic-auth")
+            .value_name("USER:PASSWORD")
+            .env("RLINKS_BASIC_AUTH")
+            .hide_env_values(true)
+            .help("Basic auth credentials. Can be read from a variable with env:VAR"),
+        Arg::with_name("bearer_token")
+            .long("bearer-token")
+            .value_name("TOKEN")
+            .env("RLINKS_BEARER_TOKEN")
+            .hide_env_values(true)
+            .help("Bearer token for the Authorization header. Can be read from a variable with env:VAR"),
+        Arg::with_name("auth_host")
+            .long("auth-host")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Only send headers and credentials to this host and its subdomains (defaults to the host being checked)"),
+        Arg::with_name("cookies")
+            .long("cookies")
+            .value_name("FILE")
+            .help("Load cookies from a Netscape cookies.txt file, as exported by browsers and curl"),
+        Arg::with_name("save_cookies")
+            .long("save-cookies")
+            .value_name("FILE")
+            .help("Save the cookies to a Netscape cookies.txt file after the run"),
+        Arg::with_name("proxy")
+            .long("proxy")
+            .value_name("URL")
+            .help("Send requests through an http, https or socks5 proxy (defaults to HTTP_PROXY/HTTPS_PROXY)"),
+        Arg::with_name("no_proxy")
+            .long("no-proxy")
+            .value_name("HOSTS")
+            .multiple(true)
+            .number_of_values(1)
+            .use_delimiter(true)
+            .help("Comma separated hosts to reach without the proxy (defaults to NO_PROXY)"),
+        Arg::with_name("ca_cert")
+            .long("ca-cert")
+            .value_name("FILE")
+            .help("Also trust the certificate authorities in this PEM bundle"),
+        Arg::with_name("client_cert")
+            .long("client-cert")
+            .value_name("FILE")
+            .help("PEM client certificate, presented to the hosts given by --auth-host"),
+        Arg::with_name("client_key")
+            .long("client-key")
+            .value_name("FILE")
+            .requires("client_cert")
+            .help("PEM private key of the client certificate"),
+        Arg::with_name("insecure")
+            .long("insecure")
+            .value_name("HOST")
+            .multiple(true)
+            .number_of_values(1)
+            .help("Don't verify the certificates of this host and its subdomains"),
+        Arg::with_name("accept")
+            .long("accept")
+            .value_name("CODES")
+            .help("Status codes that count as success, e.g. 200..=299,403,999 (defaults to 2xx and 3xx)"),
+        Arg::with_name("get_fallback")
+            .long("get-fallback")
+            .value_name("CODES")
+            .help("Status codes of a HEAD request that make us retry with GET, e.g. 403,404,501 (defaults to 405)"),
+        Arg::with_name("config")
+            .long("config")
+            .value_name("FILE")
+            .help("Json file with accept and get_fallback codes, globally or under \"hosts\" for each host"),
+    ]
+}
+
 pub fn make_app<'a, 'b>() -> App<'a, 'b> {
     App::new("Rusty Links")
         .version(crate_version!())
@@ -80,18 +355,12 @@ pub fn make_app<'a, 'b>() -> App<'a, 'b> {
         .subcommand(
             SubCommand::with_name("check")
                 .about("Checks links")
+                .args(&request_args())
                 .setting(AppSettings::ArgRequiredElseHelp)
                 .arg(
-                    Arg::with_name("URL")
-                        .index(1)
-                        .help("URL to check links for (e.g. http://www.google.com)"),
-                )
-                .arg(
-                    Arg::with_name("n_par")
-                        .short("p")
-                        .long("n_par")
-                        .help("Number of parallel requests per domain")
-                        .takes_value(true),
+                    Arg::with_name("URL").index(1).help(
+                        "URL or local directory to check links for (e.g. http://www.google.com)",
+                    ),
                 )
                 .arg(
                     Arg::with_name("show_ok")
@@ -119,16 +388,96 @@ pub fn make_app<'a, 'b>() -> App<'a, 'b> {
                         .long("ignore_urls")
                         .takes_value(true)
                         .help("Ignores certain patterns. Uses a single regex expression"),
+                )
+                .arg(
+                    Arg::with_name("json_select")
+                        .long("json-select")
+                        .value_name("SELECTOR")
+                        .help("Only look for links in these parts of json documents (e.g. $.items[*].url)"),
+                )
+                .arg(
+                    Arg::with_name("render_command")
+                        .long("render-command")
+                        .value_name("COMMAND")
+                        .help("Get the html of the start page from COMMAND, which takes its url ({url} or last argument) and prints the page after its scripts run"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&OutputFormat::variants())
+                        .case_insensitive(true)
+                        .help("Format of the report (defaults to text)"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("OUTPUT_FILE")
+                        .help("File to write the report to (defaults to stdout)"),
+                )
+                .arg(
+                    Arg::with_name("watch")
+                        .short("w")
+                        .long("watch")
+                        .help("Keep checking a local directory as its files change"),
+                )
+                .arg(
+                    Arg::with_name("baseline")
+                        .long("baseline")
+                        .value_name("REPORT_FILE")
+                        .help("Json report of a previous run to compare the results against"),
+                )
+                .arg(
+                    Arg::with_name("fail_on_new")
+                        .long("fail-on-new")
+                        .requires("baseline")
+                        .help("Exit with an error only if there are newly broken links"),
+                )
+                .arg(
+                    Arg::with_name("cert_expiry_warn")
+                        .long("cert-expiry-warn")
+                        .value_name("WINDOW")
+                        .help("Warn about https certificates expiring within WINDOW (e.g. 12h, 2w, defaults to 30d)"),
+                )
+                .arg(
+                    Arg::with_name("max_size")
+                        .long("max-size")
+                        .value_name("SIZE")
+                        .help("Flag images, scripts and stylesheets larger than SIZE (e.g. 500k, 5MB)"),
+                )
+                .arg(
+                    Arg::with_name("probe_https")
+                        .long("probe-https")
+                        .help("Check whether http resources of https pages are also served over https"),
+                )
+                .arg(
+                    Arg::with_name("suggest_https")
+                        .long("suggest-https")
+                        .help("Try the https version of every working http link and report the ones that can be upgraded"),
+                )
+                .arg(
+                    Arg::with_name("check_mail_domains")
+                        .long("check-mail-domains")
+                        .help("Check that the domains of mailto addresses have MX or A records"),
+                )
+                .arg(
+                    Arg::with_name("soft_404")
+                        .long("soft-404")
+                        .conflicts_with("watch")
+                        .help("Fetch pages that look fine and flag the ones that look like error pages"),
                 ),
         )
         .subcommand(
             SubCommand::with_name("dump")
                 .about("Dump links")
+                .args(&request_args())
                 .setting(AppSettings::ArgRequiredElseHelp)
                 .arg(
                     Arg::with_name("URL")
                         .index(1)
-                        .help("URL to check links for (e.g. http://www.google.com)"),
+                        .help("URL to dump links from (e.g. http://www.google.com)"),
                 )
                 .arg(
                     Arg::with_name("output")
@@ -157,6 +506,27 @@ pub fn make_app<'a, 'b>() -> App<'a, 'b> {
                         .long("ignore_urls")
                         .takes_value(true)
                         .help("Ignores certain patterns. Uses a single regex expression"),
+                )
+                .arg(
+                    Arg::with_name("json_select")
+                        .long("json-select")
+                        .value_name("SELECTOR")
+                        .help("Only look for links in these parts of json documents (e.g. $.items[*].url)"),
+                )
+                .arg(
+                    Arg::with_name("render_command")
+                        .long("render-command")
+                        .value_name("COMMAND")
+                        .help("Get the html of the start page from COMMAND, which takes its url ({url} or last argument) and prints the page after its scripts run"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&DumpFormat::variants())
+                        .case_insensitive(true)
+                        .help("Format of the dumped links (defaults to txt)"),
                 ),
         )
 }
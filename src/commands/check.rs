@@ -1,24 +1,221 @@
 use crate::{
-    cli::BaseConfig,
+    baseline::{compare, count, load_baseline, print_changes, Change},
+    cli::{BaseConfig, OutputFormat},
+    commands::watch::watch_site,
+    cookies::CookieSession,
     error::RLinksError,
-    req::{get_client, get_links_from_website, make_multiple_requests},
+    local::{get_links_from_site, is_local_path, site_root},
+    metadata::{check_metadata, print_metadata_issues},
+    mixed::{find_mixed_content, print_mixed_content, probe_https, suggest_https_upgrades},
+    report::{html, json, table, write_report, Report},
+    req::{get_client, get_links_from_website, make_multiple_requests, print_unchecked_schemes},
+    soft404::detect_soft_404s,
+    text::send_diagnostics_to_stderr,
+    tls::{certificate_expiries, print_expiry_warnings},
     url_fix::add_http,
 };
-use std::time::Duration;
+use std::{collections::BTreeMap, path::Path, time::Duration};
+use url::Url;
 
 pub async fn check_links(config: BaseConfig) -> Result<(), RLinksError> {
-    let client = get_client(Duration::from_secs(config.timeout));
-    let url = add_http(&config.url)?;
-    let links =
-        get_links_from_website(&client, &config.user_agent, &url, true, &config.ignore_urls)
-            .await?;
-    make_multiple_requests(
-        links,
+    if config.format != OutputFormat::Text {
+        send_diagnostics_to_stderr();
+    }
+    let cookies = CookieSession::open(&config.client)?;
+    let client = get_client(&config.client, &cookies.jar);
+    config
+        .request
+        .proxy
+        .check(Duration::from_secs(config.client.timeout))?;
+    let local = is_local_path(&config.url);
+    if config.watch {
+        if !local {
+            return Err(RLinksError::LocalPathError(config.url.into()));
+        }
+        return watch_site(Path::new(&config.url), &client, &config).await;
+    }
+    let (url, links) = if local {
+        let target = Path::new(&config.url);
+        (
+            site_root(target)?,
+            get_links_from_site(
+                target,
+                true,
+                &config.ignore_urls,
+                config.json_selector.as_ref(),
+            )
+            .await?,
+        )
+    } else {
+        let url = add_http(&config.url)?;
+        let links = get_links_from_website(
+            &client,
+            &config.request,
+            &url,
+            config.n_par,
+            true,
+            &config.ignore_urls,
+            config.json_selector.as_ref(),
+            config.render_command.as_deref(),
+        )
+        .await?;
+        (url, links)
+    };
+    let mut results = make_multiple_requests(
+        &links,
         config.n_par,
         &client,
-        &config.user_agent,
+        &config.request,
         config.show_ok,
     )
     .await;
+    print_unchecked_schemes(&links);
+    if config.soft_404 {
+        detect_soft_404s(&mut results, &client, &config.request, config.n_par).await;
+    }
+    if config.suggest_https {
+        suggest_https_upgrades(&mut results, &client, &config.request, config.n_par).await;
+    }
+    print_metadata_issues(&check_metadata(&links, &results));
+    let mut mixed = find_mixed_content(&links);
+    if config.probe_https {
+        probe_https(&mut mixed, &client, &config.request, config.n_par).await;
+    }
+    print_mixed_content(&mixed);
+    let certificates = certificate_expiries(
+        &results,
+        &config.request,
+        config.n_par,
+        config.cert_expiry_warn,
+    );
+    print_expiry_warnings(&certificates);
+    let report = Report {
+        start_url: &url,
+        links: &links,
+        results: &results,
+        certificates: &certificates,
+    };
+    let changes = write_and_compare(&config, &report)?;
+    let visited: Vec<&Url> = std::iter::once(&url).chain(links.sources.keys()).collect();
+    cookies.save(&config.client, &visited)?;
+    if let Some(changes) = changes {
+        print_changes(&changes);
+        let newly_broken = count(&changes, Change::NewlyBroken);
+        if config.fail_on_new && newly_broken > 0 {
+            return Err(RLinksError::NewlyBrokenLinks(newly_broken));
+        }
+    }
     Ok(())
 }
+
+/// Compares against the baseline before writing the report, since the report often replaces it
+fn write_and_compare(
+    config: &BaseConfig,
+    report: &Report,
+) -> Result<Option<BTreeMap<Url, Change>>, RLinksError> {
+    let changes = match &config.baseline {
+        Some(baseline) => Some(compare(
+            &load_baseline(Path::new(baseline))?,
+            report.results,
+        )),
+        None => None,
+    };
+    write_results(config, report)?;
+    Ok(changes)
+}
+
+pub fn write_results(config: &BaseConfig, report: &Report) -> Result<(), RLinksError> {
+    match config.format {
+        OutputFormat::Text => Ok(()),
+        OutputFormat::Html => write_report(&html::render(report), config.output_file.as_deref()),
+        OutputFormat::Csv => {
+            write_report(&table::render(report, b',')?, config.output_file.as_deref())
+        }
+        OutputFormat::Tsv => write_report(
+            &table::render(report, b'\t')?,
+            config.output_file.as_deref(),
+        ),
+        OutputFormat::Json => write_report(&json::render(report)?, config.output_file.as_deref()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs, time::Duration};
+
+    use http::StatusCode;
+    use tempfile::NamedTempFile;
+    use url::Url;
+
+    use crate::{
+        baseline::Change,
+        cli::{BaseConfig, ClientConfig, OutputFormat, RequestConfig},
+        commands::check::write_and_compare,
+        outcome::{CheckResult, Outcome},
+        report::{json, Report},
+        req::Links,
+        test_support::result,
+    };
+
+    fn config(path: &str) -> BaseConfig {
+        BaseConfig {
+            n_par: 1,
+            request: RequestConfig {
+                user_agent: String::new(),
+                headers: Default::default(),
+                proxy: Default::default(),
+                tls: Default::default(),
+                status: Default::default(),
+                max_resource_size: None,
+                check_mail_domains: false,
+            },
+            show_ok: false,
+            client: ClientConfig {
+                timeout: 1,
+                cookies: None,
+                save_cookies: None,
+            },
+            url: String::new(),
+            ignore_urls: None,
+            json_selector: None,
+            render_command: None,
+            format: OutputFormat::Json,
+            output_file: Some(path.to_owned()),
+            watch: false,
+            baseline: Some(path.to_owned()),
+            fail_on_new: true,
+            cert_expiry_warn: Duration::default(),
+            soft_404: false,
+            probe_https: false,
+            suggest_https: false,
+        }
+    }
+
+    fn results(outcome: Outcome) -> Vec<CheckResult> {
+        vec![result("https://example.com/a", StatusCode::OK, outcome)]
+    }
+
+    #[test]
+    fn test_baseline_is_the_output_file() {
+        let file = NamedTempFile::new().unwrap();
+        let start_url = Url::parse("https://example.com/").unwrap();
+        let links = Links::from_found(vec![]);
+        let certificates = BTreeMap::new();
+        let (before, after) = (results(Outcome::Ok), results(Outcome::HttpError));
+        let report = |results| Report {
+            start_url: &start_url,
+            links: &links,
+            results,
+            certificates: &certificates,
+        };
+        fs::write(file.path(), json::render(&report(&before)).unwrap()).unwrap();
+        let config = config(file.path().to_str().unwrap());
+        let changes = write_and_compare(&config, &report(&after))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            changes.values().collect::<Vec<_>>(),
+            vec![&Change::NewlyBroken]
+        );
+    }
+}
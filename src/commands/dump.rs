@@ -1,54 +1,66 @@
 use crate::{
-    cli::DumpConfig,
+    cli::{DumpConfig, DumpFormat},
+    cookies::CookieSession,
     error::RLinksError,
-    req::{get_client, get_links_from_website},
+    report::{json, table, write_to_file},
+    req::{get_client, get_links_from_website, Links},
     url_fix::add_http,
 };
-use std::{collections::HashSet, fs::File, io::Write, path::Path, time::Duration};
+use std::time::Duration;
 
 pub async fn dump_links(config: DumpConfig) -> Result<(), RLinksError> {
-    println!("{:?}", config);
-    let client = get_client(Duration::from_secs(config.timeout));
+    let cookies = CookieSession::open(&config.client)?;
+    let client = get_client(&config.client, &cookies.jar);
+    config
+        .request
+        .proxy
+        .check(Duration::from_secs(config.client.timeout))?;
     let url = add_http(&config.url)?;
     let links = get_links_from_website(
         &client,
-        &config.user_agent,
+        &config.request,
         &url,
+        config.n_par,
         false,
         &config.ignore_urls,
+        config.json_selector.as_ref(),
+        config.render_command.as_deref(),
     )
     .await?;
-    let all_links = links
-        .hash_map
-        .values()
-        .fold(
-            HashSet::with_capacity(links.link_count as usize),
-            |mut acc, x| {
-                x.iter().for_each(|url| {
-                    acc.insert(url.as_str());
-                });
-                acc
-            },
-        )
-        .into_iter()
-        .collect::<Vec<&str>>()
-        .join("\n");
-
-    // Open a file in write-only mode, returns `io::Result<File>`
-    write_to_file(&all_links, &config.output_file);
-    Ok(())
-}
-fn write_to_file(string: &str, output_file: &str) {
-    let path = Path::new(output_file);
-    let display = path.display();
-    let mut file = match File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why),
-        Ok(file) => file,
+    let all_links = match config.format {
+        DumpFormat::Txt => all_links_text(&links),
+        DumpFormat::Csv => table::render_links(&links, b',')?,
+        DumpFormat::Tsv => table::render_links(&links, b'\t')?,
+        DumpFormat::Json => json::render_links(&links)?,
     };
+    write_to_file(&all_links, &config.output_file)?;
+    cookies.save(&config.client, &[&url])
+}
+/// One url per line, sorted so that dumps can be diffed
+fn all_links_text(links: &Links) -> String {
+    let mut all_links: Vec<&str> = links.sources.keys().map(|url| url.as_str()).collect();
+    all_links.sort_unstable();
+    all_links.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::{commands::dump::all_links_text, req::Links, test_support::found};
 
-    // Write the `LOREM_IPSUM` string to `file`, returns `io::Result<()>`
-    match file.write_all(string.as_bytes()) {
-        Err(why) => panic!("couldn't write to {}: {}", display, why),
-        Ok(_) => println!("successfully wrote to {}", display),
+    #[test]
+    fn test_all_links_text() {
+        let page = Url::parse("https://example.com/").unwrap();
+        let links = Links::from_found(
+            ["/c", "https://a.org/", "/b"]
+                .iter()
+                .map(|href| found(&page, href, "a", &[]))
+                .collect(),
+        );
+        assert_eq!(
+            all_links_text(&links),
+            "https://a.org/\nhttps://example.com/b\nhttps://example.com/c"
+        );
     }
 }
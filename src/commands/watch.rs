@@ -0,0 +1,178 @@
+use crate::{
+    cli::BaseConfig,
+    commands::check::write_results,
+    error::RLinksError,
+    local::{get_links_from_local_page, is_page, site_files, site_root},
+    outcome::CheckResult,
+    report::Report,
+    req::{make_multiple_requests, print_unchecked_schemes, LinkSource, Links},
+    text::{print_diagnostic, ColorsExt},
+    tls::{certificate_expiries, host_key, print_expiry_warnings, CertificateExpiry},
+};
+use console::Term;
+use isahc::HttpClient;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+use url::Url;
+
+// Editors and site generators write several files in a row, so we wait for things to settle
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+type PageLinks = HashMap<PathBuf, Vec<(Url, LinkSource)>>;
+
+async fn read_page(pages: &mut PageLinks, path: &Path, site_root: &Url, config: &BaseConfig) {
+    match get_links_from_local_page(
+        path,
+        site_root,
+        true,
+        &config.ignore_urls,
+        config.json_selector.as_ref(),
+    )
+    .await
+    {
+        Ok(found) => {
+            pages.insert(path.to_owned(), found);
+        }
+        Err(e) => {
+            format!("Could not read {}: {}", path.display(), e).print_in_red();
+            pages.remove(path);
+        }
+    }
+}
+
+/// Blocks until at least one page changes, then returns every page that changed
+fn wait_for_changes(
+    rx: &Receiver<notify::Result<notify::Event>>,
+) -> Result<BTreeSet<PathBuf>, RLinksError> {
+    let mut changed = BTreeSet::new();
+    loop {
+        let event = if changed.is_empty() {
+            // The watcher lives as long as the receiver, so the channel can't be closed
+            rx.recv().unwrap()
+        } else {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => return Ok(changed),
+                Err(RecvTimeoutError::Disconnected) => unreachable!(),
+            }
+        }?;
+        if let EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) = event.kind {
+            changed.extend(event.paths.into_iter().filter(|path| is_page(path)));
+        }
+    }
+}
+
+fn print_summary(target: &Path, links: &Links, results: &[CheckResult]) {
+    // Clearing can only fail if stdout is not a terminal, in which case we just keep printing
+    let _ = Term::stdout().clear_screen();
+    let mut broken: Vec<&CheckResult> = results.iter().filter(|r| r.outcome.is_broken()).collect();
+    broken.sort_by(|a, b| a.url.as_str().cmp(b.url.as_str()));
+    let summary = format!("{} links checked, {} broken", results.len(), broken.len());
+    if broken.is_empty() {
+        summary.print_in_green();
+    } else {
+        summary.print_in_red();
+    }
+    for result in broken {
+        format!(
+            "{} ({}, {})",
+            result.url,
+            result.status,
+            result.outcome.label()
+        )
+        .print_in_red();
+        for source in links.sources.get(&result.url).into_iter().flatten() {
+            print_diagnostic(&format!("    in {}", source.page));
+        }
+    }
+    print_unchecked_schemes(links);
+    print_diagnostic(&format!("Watching {} for changes...", target.display()));
+}
+
+/// Checks a local site, then re-checks it every time one of its pages changes. Only the pages
+/// that changed are parsed again, and only links that were not seen before are requested
+pub async fn watch_site(
+    target: &Path,
+    client: &HttpClient,
+    config: &BaseConfig,
+) -> Result<(), RLinksError> {
+    let target = target.canonicalize()?;
+    let site_root = site_root(&target)?;
+    let mut pages: PageLinks = HashMap::new();
+    for path in site_files(&target)? {
+        read_page(&mut pages, &path, &site_root, config).await;
+    }
+    let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(&target, RecursiveMode::Recursive)?;
+
+    let mut cache: HashMap<Url, CheckResult> = HashMap::new();
+    let mut certificates: BTreeMap<String, CertificateExpiry> = BTreeMap::new();
+    loop {
+        let links = Links::from_found(pages.values().flatten().cloned().collect());
+        // Whether a local file exists can change between runs, and it is cheap to check again
+        cache.retain(|url, _| url.scheme() != "file");
+        let unchecked = Links::from_found(
+            links
+                .sources
+                .iter()
+                .filter(|(url, _)| !cache.contains_key(url))
+                .flat_map(|(url, sources)| sources.iter().map(move |s| (url.clone(), s.clone())))
+                .collect(),
+        );
+        let checked = make_multiple_requests(
+            &unchecked,
+            config.n_par,
+            client,
+            &config.request,
+            config.show_ok,
+        )
+        .await;
+        // Certificates are only looked up for hosts we haven't seen yet
+        let new_hosts: Vec<CheckResult> = checked
+            .iter()
+            .filter(|result| {
+                host_key(&result.url).is_some_and(|key| !certificates.contains_key(&key))
+            })
+            .cloned()
+            .collect();
+        certificates.extend(certificate_expiries(
+            &new_hosts,
+            &config.request,
+            config.n_par,
+            config.cert_expiry_warn,
+        ));
+        checked.into_iter().for_each(|result| {
+            cache.insert(result.url.clone(), result);
+        });
+        let results: Vec<CheckResult> = links
+            .sources
+            .keys()
+            .filter_map(|url| cache.get(url).cloned())
+            .collect();
+        print_summary(&target, &links, &results);
+        print_expiry_warnings(&certificates);
+        write_results(
+            config,
+            &Report {
+                start_url: &site_root,
+                links: &links,
+                results: &results,
+                certificates: &certificates,
+            },
+        )?;
+
+        for path in wait_for_changes(&rx)? {
+            if path.is_file() {
+                read_page(&mut pages, &path, &site_root, config).await;
+            } else {
+                pages.remove(&path);
+            }
+        }
+    }
+}
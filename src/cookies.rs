@@ -0,0 +1,224 @@
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use http::Uri;
+use isahc::cookies::{Cookie, CookieJar};
+use url::Url;
+
+use crate::{cli::ClientConfig, error::RLinksError, report::write_to_file};
+
+// curl and browsers mark http-only cookies by prefixing the domain
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+/// A line of a Netscape cookies.txt file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CookieRecord {
+    pub http_only: bool,
+    pub domain: String,
+    pub include_subdomains: bool,
+    pub path: String,
+    pub secure: bool,
+    /// Unix timestamp, 0 for session cookies
+    pub expires: u64,
+    pub name: String,
+    pub value: String,
+}
+
+impl CookieRecord {
+    fn parse(line: &str) -> Option<Self> {
+        let (http_only, line) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+            Some(line) => (true, line),
+            None if line.starts_with('#') => return None,
+            None => (false, line),
+        };
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            return None;
+        }
+        Some(CookieRecord {
+            http_only,
+            domain: fields[0].to_owned(),
+            include_subdomains: fields[1].eq_ignore_ascii_case("TRUE"),
+            path: fields[2].to_owned(),
+            secure: fields[3].eq_ignore_ascii_case("TRUE"),
+            expires: fields[4].parse().ok()?,
+            name: fields[5].to_owned(),
+            value: fields[6].to_owned(),
+        })
+    }
+    fn to_line(&self) -> String {
+        let flag = |b: bool| if b { "TRUE" } else { "FALSE" };
+        format!(
+            "{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            if self.http_only { HTTP_ONLY_PREFIX } else { "" },
+            self.domain,
+            flag(self.include_subdomains),
+            self.path,
+            flag(self.secure),
+            self.expires,
+            self.name,
+            self.value
+        )
+    }
+    fn host(&self) -> &str {
+        self.domain.trim_start_matches('.')
+    }
+    fn matches_host(&self, host: &str) -> bool {
+        host == self.host()
+            || (self.include_subdomains
+                && host
+                    .strip_suffix(self.host())
+                    .is_some_and(|sub| sub.ends_with('.')))
+    }
+    fn to_cookie(&self) -> Option<Cookie> {
+        let mut builder = Cookie::builder(self.name.as_str(), self.value.as_str())
+            .path(self.path.as_str())
+            .secure(self.secure);
+        // Cookies with a domain are sent to subdomains too, the rest only to their host
+        if self.include_subdomains {
+            builder = builder.domain(self.host());
+        }
+        if self.expires > 0 {
+            builder = builder.expiration(UNIX_EPOCH + Duration::from_secs(self.expires));
+        }
+        builder.build().ok()
+    }
+}
+
+/// The cookie jar shared by every request, along with the cookies it was filled with
+pub struct CookieSession {
+    pub jar: CookieJar,
+    loaded: Vec<CookieRecord>,
+}
+
+impl CookieSession {
+    pub fn open(config: &ClientConfig) -> Result<Self, RLinksError> {
+        let jar = CookieJar::new();
+        let loaded = match &config.cookies {
+            Some(path) => load_cookies(Path::new(path), &jar)?,
+            None => vec![],
+        };
+        Ok(CookieSession { jar, loaded })
+    }
+    /// Saves the jar if asked to, `visited` are the urls that were requested
+    pub fn save(self, config: &ClientConfig, visited: &[&Url]) -> Result<(), RLinksError> {
+        match &config.save_cookies {
+            Some(path) => save_cookies(Path::new(path), &self.jar, self.loaded, visited),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn parse_cookie_file(contents: &str) -> Vec<CookieRecord> {
+    contents
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .filter_map(CookieRecord::parse)
+        .collect()
+}
+
+/// Fills the jar with the cookies of a cookies.txt file and returns them, so that they can be
+/// saved again later
+pub fn load_cookies(path: &Path, jar: &CookieJar) -> Result<Vec<CookieRecord>, RLinksError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut records = parse_cookie_file(&fs::read_to_string(path)?);
+    records.retain(|r| r.expires == 0 || r.expires > now);
+    for record in &records {
+        let uri: Option<Uri> = format!("https://{}{}", record.host(), record.path)
+            .parse()
+            .ok();
+        let set = match (record.to_cookie(), uri) {
+            (Some(cookie), Some(uri)) => jar.set(cookie, &uri).is_ok(),
+            _ => false,
+        };
+        if !set {
+            warn!("Skipping cookie {} for {}", record.name, record.domain);
+        }
+    }
+    info!("Loaded {} cookies from {}", records.len(), path.display());
+    Ok(records)
+}
+
+/// Writes the cookies of the jar in the cookies.txt format. The jar can only be asked for the
+/// cookies it would send to an url, so we look at every url we visited: cookies we loaded get
+/// their values updated, and cookies the sites set are saved for the host that set them
+pub fn save_cookies(
+    path: &Path,
+    jar: &CookieJar,
+    mut records: Vec<CookieRecord>,
+    visited: &[&Url],
+) -> Result<(), RLinksError> {
+    for url in visited {
+        let (host, uri) = match (url.host_str(), url.as_str().parse::<Uri>()) {
+            (Some(host), Ok(uri)) => (host, uri),
+            _ => continue,
+        };
+        for cookie in jar.get_for_uri(&uri) {
+            let existing = records
+                .iter_mut()
+                .find(|r| r.name == cookie.name() && r.matches_host(host));
+            match existing {
+                Some(record) => record.value = cookie.value().to_owned(),
+                None => records.push(CookieRecord {
+                    http_only: false,
+                    domain: host.to_owned(),
+                    include_subdomains: false,
+                    path: "/".to_owned(),
+                    secure: url.scheme() == "https",
+                    expires: 0,
+                    name: cookie.name().to_owned(),
+                    value: cookie.value().to_owned(),
+                }),
+            }
+        }
+    }
+    let lines: Vec<String> = records.iter().map(CookieRecord::to_line).collect();
+    write_to_file(
+        &format!("# Netscape HTTP Cookie File\n{}\n", lines.join("\n")),
+        &path.to_string_lossy(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cookies::{parse_cookie_file, CookieRecord};
+
+    #[test]
+    fn test_parse_cookie_file() {
+        let contents = "# Netscape HTTP Cookie File\n\
+                        \n\
+                        .example.com\tTRUE\t/\tTRUE\t1900000000\tsession\tabc\n\
+                        #HttpOnly_intranet.local\tFALSE\t/docs\tFALSE\t0\tid\t42\n\
+                        malformed line\n";
+        let records = parse_cookie_file(contents);
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0],
+            CookieRecord {
+                http_only: false,
+                domain: ".example.com".to_owned(),
+                include_subdomains: true,
+                path: "/".to_owned(),
+                secure: true,
+                expires: 1900000000,
+                name: "session".to_owned(),
+                value: "abc".to_owned(),
+            }
+        );
+        assert!(records[1].http_only);
+        assert_eq!(records[1].domain, "intranet.local");
+        assert_eq!(records[1].path, "/docs");
+        assert!(records[0].matches_host("docs.example.com"));
+        assert!(!records[1].matches_host("sub.intranet.local"));
+        assert_eq!(
+            records[1].to_line(),
+            "#HttpOnly_intranet.local\tFALSE\t/docs\tFALSE\t0\tid\t42"
+        );
+    }
+}
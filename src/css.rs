@@ -0,0 +1,187 @@
+use std::{collections::HashSet, future::Future};
+
+use futures::future::join_all;
+use regex::Regex;
+use url::Url;
+
+use crate::{
+    extract::RawLink,
+    req::{resolve_links, LinkSource},
+    resource::ResourceKind,
+};
+
+fn skip_whitespace(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+/// Reads a quoted string starting at `i`, returning it and the index after the closing quote
+fn read_string(css: &str, i: usize) -> Option<(&str, usize)> {
+    let quote = *css.as_bytes().get(i)?;
+    if quote != b'"' && quote != b'\'' {
+        return None;
+    }
+    let end = i + 1 + css[i + 1..].find(quote as char)?;
+    Some((&css[i + 1..end], end + 1))
+}
+
+/// Reads the inside of `url(...)`, with `i` right after the opening parenthesis
+fn read_url(css: &str, i: usize) -> Option<(&str, usize)> {
+    let i = skip_whitespace(css.as_bytes(), i);
+    let close = |from: usize| css[from..].find(')').map(|end| from + end + 1);
+    match read_string(css, i) {
+        Some((value, after)) => Some((value, close(after)?)),
+        None => {
+            let end = i + css[i..].find(')')?;
+            Some((css[i..end].trim(), end + 1))
+        }
+    }
+}
+
+/// Every url a stylesheet refers to, along with whether it comes from `url()` or `@import`.
+/// Comments are skipped, as are references to fragments such as svg filters
+pub fn css_urls(css: &str) -> Vec<(&str, &'static str)> {
+    let bytes = css.as_bytes();
+    let starts_with = |i: usize, prefix: &str| {
+        bytes.len() >= i + prefix.len()
+            && bytes[i..i + prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+    };
+    let mut found = vec![];
+    // Set after an @import, so that the url that follows it is marked as an import
+    let mut import = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        let read = if starts_with(i, "/*") {
+            i = css[i + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |end| i + end + 4);
+            continue;
+        } else if starts_with(i, "@import") {
+            import = true;
+            i = skip_whitespace(bytes, i + "@import".len());
+            read_string(css, i)
+        } else if starts_with(i, "url(") {
+            read_url(css, i + "url(".len())
+        } else {
+            i += 1;
+            continue;
+        };
+        match read {
+            Some((value, after)) => {
+                let kind = if import { "@import" } else { "url" };
+                if !value.is_empty() && !value.starts_with('#') {
+                    found.push((value, kind));
+                }
+                import = false;
+                i = after;
+            }
+            // An @import followed by url() is read in the next iteration
+            None if import && starts_with(i, "url(") => (),
+            None => {
+                import = false;
+                i += 1;
+            }
+        }
+    }
+    found
+}
+
+/// The links of a stylesheet, as they would be found in an element named `element`
+pub fn css_links<'a>(css: &'a str, element: &'a str) -> Vec<RawLink<'a>> {
+    css_urls(css)
+        .into_iter()
+        .map(|(href, attribute)| RawLink {
+            href,
+            element,
+            attribute,
+            text: String::new(),
+            rel: vec![],
+            descriptor: None,
+        })
+        .collect()
+}
+
+/// Fetches every stylesheet among `found`, and the ones they import, and returns the links in
+/// them. Those are resolved against the stylesheet, which is reported as the page they are in
+pub async fn follow_stylesheets<F, Fut>(
+    found: &[(Url, LinkSource)],
+    fetch: F,
+    site_root: Option<&Url>,
+    truncate_fragments: bool,
+    regex: &Option<Regex>,
+) -> Vec<(Url, LinkSource)>
+where
+    F: Fn(Url) -> Fut,
+    Fut: Future<Output = Option<String>>,
+{
+    let is_stylesheet = |(_, source): &&(Url, LinkSource)| {
+        ResourceKind::of(source) == Some(ResourceKind::Stylesheet)
+    };
+    let mut visited: HashSet<Url> = HashSet::new();
+    let mut pending: Vec<Url> = found
+        .iter()
+        .filter(is_stylesheet)
+        .map(|(url, _)| url.clone())
+        .filter(|url| visited.insert(url.clone()))
+        .collect();
+    let mut links = vec![];
+    while !pending.is_empty() {
+        let stylesheets = join_all(pending.iter().cloned().map(&fetch)).await;
+        let mut imported = vec![];
+        for (url, css) in pending.iter().zip(stylesheets) {
+            let css = match css {
+                Some(css) => css,
+                None => continue,
+            };
+            let found = resolve_links(
+                css_links(&css, "css"),
+                url,
+                site_root,
+                truncate_fragments,
+                regex,
+            );
+            imported.extend(
+                found
+                    .iter()
+                    .filter(is_stylesheet)
+                    .map(|(url, _)| url.clone()),
+            );
+            links.extend(found);
+        }
+        pending = imported
+            .into_iter()
+            .filter(|url| visited.insert(url.clone()))
+            .collect();
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::css::css_urls;
+
+    #[test]
+    fn test_css_urls() {
+        let css = r#"
+            @import "base.css";
+            @import url('print.css') print;
+            /* background: url(commented.png); */
+            body { background: URL( "bg.png" ) no-repeat; }
+            @font-face { src: url(fonts/a.woff2) format("woff2"), url(data:font/woff;base64,AA) }
+            rect { filter: url(#blur); }
+        "#;
+        assert_eq!(
+            css_urls(css),
+            vec![
+                ("base.css", "@import"),
+                ("print.css", "@import"),
+                ("bg.png", "url"),
+                ("fonts/a.woff2", "url"),
+                ("data:font/woff;base64,AA", "url"),
+            ]
+        );
+        assert_eq!(css_urls("a{b:url(unclosed"), vec![]);
+    }
+}
@@ -0,0 +1,95 @@
+use http::StatusCode;
+use url::{Position, Url};
+
+use crate::outcome::{CheckResult, Outcome};
+
+fn is_base64(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'+' || c == b'/'
+}
+
+/// Whether every `%` starts a valid escape
+fn is_percent_encoded(data: &str) -> bool {
+    let bytes = data.as_bytes();
+    bytes.iter().enumerate().all(|(i, b)| {
+        *b != b'%'
+            || bytes
+                .get(i + 1..i + 3)
+                .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+    })
+}
+
+/// Whether `data` decodes as base64. Whitespace and missing padding are forgiven, as browsers
+/// do
+fn is_base64_encoded(data: &str) -> bool {
+    let decoded = percent_encoding::percent_decode_str(data).collect::<Vec<u8>>();
+    let mut data: Vec<u8> = decoded
+        .into_iter()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    if data.len().is_multiple_of(4) {
+        for _ in 0..2 {
+            if data.last() == Some(&b'=') {
+                data.pop();
+            }
+        }
+    }
+    data.len() % 4 != 1 && data.iter().all(|b| is_base64(*b))
+}
+
+/// What is wrong with a data url, if anything
+fn data_url_problem(url: &Url) -> Option<&'static str> {
+    let contents = &url[Position::BeforePath..Position::AfterQuery];
+    let (metadata, data) = match contents.split_once(',') {
+        Some(split) => split,
+        None => return Some("missing comma"),
+    };
+    if !is_percent_encoded(data) {
+        return Some("invalid percent encoding");
+    }
+    let base64 = metadata
+        .rsplit(';')
+        .next()
+        .is_some_and(|last| last.trim().eq_ignore_ascii_case("base64"));
+    if base64 && !is_base64_encoded(data) {
+        return Some("invalid base64");
+    }
+    None
+}
+
+/// A data url is fine if its contents can be decoded
+pub fn check_data_url(url: &Url) -> CheckResult {
+    let (status, outcome) = match data_url_problem(url) {
+        None => (StatusCode::OK, Outcome::Ok),
+        Some(problem) => {
+            info!("[ERROR] {} in data url", problem);
+            (StatusCode::BAD_REQUEST, Outcome::InvalidData)
+        }
+    };
+    CheckResult::new(url, status, outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::data_url::data_url_problem;
+
+    #[test]
+    fn test_data_url_problem() {
+        let problem = |url: &str| data_url_problem(&Url::parse(url).unwrap());
+        assert_eq!(problem("data:,Hello%2C%20World!"), None);
+        assert_eq!(problem("data:text/plain;base64,SGVsbG8sIFdvcmxkIQ=="), None);
+        assert_eq!(problem("data:image/png;base64,iVBO Rw0K"), None);
+        assert_eq!(problem("data:;base64,SGk"), None);
+        assert_eq!(
+            problem("data:text/plain,100%"),
+            Some("invalid percent encoding")
+        );
+        assert_eq!(
+            problem("data:image/png;base64,iVBO*w0K"),
+            Some("invalid base64")
+        );
+        assert_eq!(problem("data:;base64,SGVsb"), Some("invalid base64"));
+        assert_eq!(problem("data:text/plain"), Some("missing comma"));
+    }
+}
@@ -1,4 +1,7 @@
-use std::fmt::{Display, Error as FmtErr, Formatter};
+use std::{
+    fmt::{Display, Error as FmtErr, Formatter},
+    path::PathBuf,
+};
 
 use http::StatusCode;
 use url::Url;
@@ -8,8 +11,22 @@ pub enum RLinksError {
     RequestError(isahc::Error),
     ArgumentParsingError(clap::Error),
     StatusCodeError(StatusCode, Url),
-    IgnoredPattern(String, String),
     RegexParsingError(regex::Error),
+    IoError(std::io::Error),
+    CsvError(csv::Error),
+    JsonError(serde_json::Error),
+    LocalPathError(PathBuf),
+    WatchError(notify::Error),
+    NewlyBrokenLinks(usize),
+    HeaderParseError(String),
+    MissingEnvVar(String),
+    ProxyParseError(String),
+    ProxyUnreachable(String),
+    DurationParseError(String),
+    StatusRangeParseError(String),
+    SizeParseError(String),
+    SelectorParseError(String),
+    RenderError(Url, String),
 }
 
 impl From<url::ParseError> for RLinksError {
@@ -33,6 +50,26 @@ impl From<regex::Error> for RLinksError {
         RLinksError::RegexParsingError(err)
     }
 }
+impl From<std::io::Error> for RLinksError {
+    fn from(err: std::io::Error) -> RLinksError {
+        RLinksError::IoError(err)
+    }
+}
+impl From<csv::Error> for RLinksError {
+    fn from(err: csv::Error) -> RLinksError {
+        RLinksError::CsvError(err)
+    }
+}
+impl From<serde_json::Error> for RLinksError {
+    fn from(err: serde_json::Error) -> RLinksError {
+        RLinksError::JsonError(err)
+    }
+}
+impl From<notify::Error> for RLinksError {
+    fn from(err: notify::Error) -> RLinksError {
+        RLinksError::WatchError(err)
+    }
+}
 impl Display for RLinksError {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtErr> {
         match self {
@@ -43,11 +80,49 @@ impl Display for RLinksError {
                 "Could not reach {} (Status code: {})",
                 url, status
             )),
-            RLinksError::IgnoredPattern(url, pattern) => f.write_str(&format!(
-                "Ignored url {} because it matches {}",
-                url, pattern
-            )),
             RLinksError::RegexParsingError(ref err) => err.fmt(f),
+            RLinksError::IoError(ref err) => err.fmt(f),
+            RLinksError::CsvError(ref err) => err.fmt(f),
+            RLinksError::JsonError(ref err) => err.fmt(f),
+            RLinksError::LocalPathError(path) => {
+                f.write_str(&format!("Could not use local path {}", path.display()))
+            }
+            RLinksError::WatchError(ref err) => err.fmt(f),
+            RLinksError::HeaderParseError(header) => {
+                f.write_str(&format!("Could not parse header {}", header))
+            }
+            RLinksError::MissingEnvVar(name) => {
+                f.write_str(&format!("Environment variable {} is not set", name))
+            }
+            RLinksError::ProxyParseError(proxy) => f.write_str(&format!(
+                "Could not parse proxy {}, it should be an http, https or socks5 url",
+                proxy
+            )),
+            RLinksError::ProxyUnreachable(proxy) => {
+                f.write_str(&format!("Could not connect to proxy {}", proxy))
+            }
+            RLinksError::DurationParseError(window) => f.write_str(&format!(
+                "Could not parse {} as a duration, use something like 30d, 12h or 2w",
+                window
+            )),
+            RLinksError::StatusRangeParseError(ranges) => f.write_str(&format!(
+                "Could not parse status codes {}, use something like 200..=299,403",
+                ranges
+            )),
+            RLinksError::SizeParseError(size) => f.write_str(&format!(
+                "Could not parse {} as a size, use something like 500k or 5MB",
+                size
+            )),
+            RLinksError::SelectorParseError(selector) => f.write_str(&format!(
+                "Could not parse json selector {}, use something like $.items[*].url",
+                selector
+            )),
+            RLinksError::RenderError(url, stderr) => {
+                f.write_str(&format!("Could not render {}: {}", url, stderr))
+            }
+            RLinksError::NewlyBrokenLinks(count) => {
+                f.write_str(&format!("Found {} newly broken links", count))
+            }
         }
     }
 }
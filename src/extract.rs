@@ -0,0 +1,343 @@
+use select::{
+    document::Document,
+    node::Node,
+    predicate::{Attr, Name},
+};
+
+use crate::css::css_links;
+
+/// A link as it appears in a page, before it is resolved against the page url
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawLink<'a> {
+    pub href: &'a str,
+    pub element: &'a str,
+    pub attribute: &'static str,
+    pub text: String,
+    /// Link types from the `rel` attribute, such as nofollow, sponsored or ugc
+    pub rel: Vec<&'a str>,
+    /// Width or density descriptor of a `srcset` candidate, such as `640w` or `2x`
+    pub descriptor: Option<&'a str>,
+}
+
+/// Collapses the whitespace in the text of a node, as a browser would render it
+fn node_text(node: &Node) -> String {
+    node.text()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn get_rel<'a>(node: &Node<'a>) -> Vec<&'a str> {
+    node.attr("rel")
+        .map(|rel| rel.split_whitespace().collect())
+        .unwrap_or_default()
+}
+
+pub fn get_raw_links(body: &Document) -> Vec<RawLink<'_>> {
+    get_href_links(body)
+        .into_iter()
+        .chain(get_img_links(body))
+        .chain(get_script_links(body))
+        .chain(get_stylesheet_links(body))
+        .chain(get_iframe_links(body))
+        .chain(get_style_links(body))
+        .chain(get_srcset_links(body))
+        .chain(get_metadata_links(body))
+        .collect()
+}
+
+fn get_href_links(body: &Document) -> Vec<RawLink<'_>> {
+    body.find(Name("a"))
+        .filter_map(|n| {
+            n.attr("href").map(|href| RawLink {
+                href,
+                element: "a",
+                attribute: "href",
+                text: node_text(&n),
+                rel: get_rel(&n),
+                descriptor: None,
+            })
+        })
+        .collect()
+}
+fn get_img_links(body: &Document) -> Vec<RawLink<'_>> {
+    body.find(Name("img"))
+        .filter_map(|n| {
+            n.attr("src").map(|href| RawLink {
+                href,
+                element: "img",
+                attribute: "src",
+                text: n.attr("alt").unwrap_or_default().to_owned(),
+                rel: vec![],
+                descriptor: None,
+            })
+        })
+        .collect()
+}
+fn get_script_links(body: &Document) -> Vec<RawLink<'_>> {
+    body.find(Name("script"))
+        .filter_map(|n| {
+            n.attr("src").map(|href| RawLink {
+                href,
+                element: "script",
+                attribute: "src",
+                text: String::new(),
+                rel: vec![],
+                descriptor: None,
+            })
+        })
+        .collect()
+}
+fn get_stylesheet_links(body: &Document) -> Vec<RawLink<'_>> {
+    body.find(Name("link"))
+        .filter(|n| {
+            get_rel(n)
+                .iter()
+                .any(|rel| rel.eq_ignore_ascii_case("stylesheet"))
+        })
+        .filter_map(|n| {
+            n.attr("href").map(|href| RawLink {
+                href,
+                element: "link",
+                attribute: "href",
+                text: String::new(),
+                rel: get_rel(&n),
+                descriptor: None,
+            })
+        })
+        .collect()
+}
+fn get_iframe_links(body: &Document) -> Vec<RawLink<'_>> {
+    body.find(Name("iframe"))
+        .filter_map(|n| {
+            n.attr("src").map(|href| RawLink {
+                href,
+                element: "iframe",
+                attribute: "src",
+                text: n.attr("title").unwrap_or_default().to_owned(),
+                rel: vec![],
+                descriptor: None,
+            })
+        })
+        .collect()
+}
+/// Urls in `<style>` blocks and in the style attribute of any element
+fn get_style_links(body: &Document) -> Vec<RawLink<'_>> {
+    let blocks = body
+        .find(Name("style"))
+        .flat_map(|n| n.children().filter_map(|child| child.as_text()))
+        .flat_map(|css| css_links(css, "style"));
+    let attributes = body
+        .find(Attr("style", ()))
+        .flat_map(|n| {
+            let element = n.name().unwrap_or_default();
+            css_links(n.attr("style").unwrap_or_default(), element)
+        })
+        .map(|link| RawLink {
+            attribute: "style",
+            ..link
+        });
+    blocks.chain(attributes).collect()
+}
+/// Splits a `srcset` into its candidate urls and their descriptors, following the html spec:
+/// urls end at whitespace, so they can contain commas, and descriptors end at a comma that is
+/// not inside parentheses
+pub fn parse_srcset(srcset: &str) -> Vec<(&str, Option<&str>)> {
+    let mut candidates = vec![];
+    let mut rest = srcset;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            return candidates;
+        }
+        let url_end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let (url, after) = rest.split_at(url_end);
+        rest = after;
+        // A url that ends in commas has no descriptors
+        let trimmed = url.trim_end_matches(',');
+        if trimmed.len() != url.len() {
+            candidates.push((trimmed, None));
+            continue;
+        }
+        let mut depth = 0usize;
+        let descriptor_end = rest
+            .char_indices()
+            .find(|(_, c)| {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth = depth.saturating_sub(1),
+                    ',' => return depth == 0,
+                    _ => (),
+                }
+                false
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        let (descriptor, after) = rest.split_at(descriptor_end);
+        rest = after;
+        let descriptor = descriptor.trim();
+        candidates.push((url, Some(descriptor).filter(|d| !d.is_empty())));
+    }
+}
+
+/// Every candidate of `srcset` in images and picture sources, and of `imagesrcset` in preloads
+fn get_srcset_links(body: &Document) -> Vec<RawLink<'_>> {
+    [
+        ("img", "srcset"),
+        ("source", "srcset"),
+        ("link", "imagesrcset"),
+    ]
+    .iter()
+    .flat_map(|&(element, attribute)| {
+        body.find(Name(element)).flat_map(move |n| {
+            let text = n.attr("alt").unwrap_or_default().to_owned();
+            parse_srcset(n.attr(attribute).unwrap_or_default())
+                .into_iter()
+                .map(move |(href, descriptor)| RawLink {
+                    href,
+                    element,
+                    attribute,
+                    text: text.clone(),
+                    rel: vec![],
+                    descriptor,
+                })
+        })
+    })
+    .collect()
+}
+/// Link types of `<link>` elements that point to something search engines or browsers use
+const METADATA_RELS: [&str; 6] = [
+    "canonical",
+    "alternate",
+    "icon",
+    "apple-touch-icon",
+    "mask-icon",
+    "manifest",
+];
+/// Open Graph and Twitter card properties whose content is a url
+const METADATA_PROPERTIES: [&str; 6] = [
+    "og:url",
+    "og:image",
+    "og:image:url",
+    "og:image:secure_url",
+    "twitter:image",
+    "twitter:image:src",
+];
+
+/// Canonical, alternate and icon links, and urls in Open Graph and Twitter card metadata. The
+/// text of alternates is their hreflang, and that of metadata its property
+fn get_metadata_links(body: &Document) -> Vec<RawLink<'_>> {
+    let links = body
+        .find(Name("link"))
+        .filter(|n| {
+            get_rel(n)
+                .iter()
+                .any(|rel| METADATA_RELS.iter().any(|m| rel.eq_ignore_ascii_case(m)))
+        })
+        .filter_map(|n| {
+            n.attr("href").map(|href| RawLink {
+                href,
+                element: "link",
+                attribute: "href",
+                text: n.attr("hreflang").unwrap_or_default().to_owned(),
+                rel: get_rel(&n),
+                descriptor: None,
+            })
+        });
+    let meta = body.find(Name("meta")).filter_map(|n| {
+        let property = n.attr("property").or_else(|| n.attr("name"))?;
+        let property = property.trim().to_lowercase();
+        if !METADATA_PROPERTIES.contains(&property.as_str()) {
+            return None;
+        }
+        n.attr("content").map(|href| RawLink {
+            href,
+            element: "meta",
+            attribute: "content",
+            text: property,
+            rel: vec![],
+            descriptor: None,
+        })
+    });
+    links.chain(meta).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use select::document::Document;
+
+    use crate::extract::{get_raw_links, parse_srcset};
+
+    #[test]
+    fn test_get_raw_links() {
+        let body = Document::from(
+            "<head><link rel=\"Stylesheet\" href=\"/c.css\"><link rel=\"preconnect\" href=\"/d\">\
+             <script src=\"/e.js\"></script><script>inline()</script>\
+             <style>@import 'f.css'; body { background: url(g.png) }</style></head>\
+             <p style=\"background-image: url('/h.jpg')\"><a href=\"/a\" rel=\"nofollow  ugc\">Some\n   <b>text</b></a><img src=\"/b.png\" alt=\"Alt\"><a>none</a></p>",
+        );
+        let links = get_raw_links(&body);
+        assert_eq!(links.len(), 7);
+        assert_eq!(links[0].href, "/a");
+        assert_eq!(links[0].element, "a");
+        assert_eq!(links[0].text, "Some text");
+        assert_eq!(links[0].rel, vec!["nofollow", "ugc"]);
+        assert_eq!(links[1].href, "/b.png");
+        assert_eq!(links[1].attribute, "src");
+        assert_eq!(links[1].text, "Alt");
+        assert_eq!((links[2].href, links[2].element), ("/e.js", "script"));
+        assert_eq!((links[3].href, links[3].element), ("/c.css", "link"));
+        assert_eq!((links[4].href, links[4].attribute), ("f.css", "@import"));
+        assert_eq!((links[5].href, links[5].attribute), ("g.png", "url"));
+        assert_eq!((links[6].href, links[6].element), ("/h.jpg", "p"));
+    }
+
+    #[test]
+    fn test_get_metadata_links() {
+        let body = Document::from(
+            "<head><link rel=\"canonical\" href=\"https://a.com/\">\
+             <link rel=\"alternate\" hreflang=\"de\" href=\"https://a.com/de/\">\
+             <link rel=\"shortcut icon\" href=\"/favicon.ico\"><link rel=\"manifest\" href=\"/m.json\">\
+             <meta property=\"og:image\" content=\"https://a.com/og.png\">\
+             <meta name=\"Twitter:Image\" content=\"/tw.png\">\
+             <meta name=\"description\" content=\"not a url\"></head>",
+        );
+        let links: Vec<(&str, String)> = get_raw_links(&body)
+            .into_iter()
+            .map(|link| (link.href, link.text))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                ("https://a.com/", String::new()),
+                ("https://a.com/de/", "de".to_owned()),
+                ("/favicon.ico", String::new()),
+                ("/m.json", String::new()),
+                ("https://a.com/og.png", "og:image".to_owned()),
+                ("/tw.png", "twitter:image".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_srcset() {
+        assert_eq!(
+            parse_srcset(" a.jpg 1x,\n b,c.jpg 2x , d.jpg,, e.jpg 640w 480h"),
+            vec![
+                ("a.jpg", Some("1x")),
+                ("b,c.jpg", Some("2x")),
+                ("d.jpg", None),
+                ("e.jpg", Some("640w 480h")),
+            ]
+        );
+        assert_eq!(
+            parse_srcset("data:image/png;base64,AAAA 1x, f.jpg (odd, but) 2x"),
+            vec![
+                ("data:image/png;base64,AAAA", Some("1x")),
+                ("f.jpg", Some("(odd, but) 2x")),
+            ]
+        );
+        assert_eq!(parse_srcset(" , "), vec![]);
+    }
+}
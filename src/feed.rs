@@ -0,0 +1,177 @@
+use regex::Regex;
+use roxmltree::{Document as XmlDocument, Node};
+use select::{
+    document::Document,
+    predicate::{Attr, Name, Predicate},
+};
+use url::Url;
+
+use crate::{
+    extract::RawLink,
+    req::{resolve_links, LinkSource},
+};
+
+const FEED_TYPES: [&str; 3] = [
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/rdf+xml",
+];
+
+/// Feeds a page advertises with `<link rel=alternate>`
+pub fn discover_feeds(body: &Document, page: &Url) -> Vec<Url> {
+    body.find(Name("link").and(Attr("rel", "alternate")))
+        .filter(|n| {
+            n.attr("type")
+                .is_some_and(|kind| FEED_TYPES.contains(&kind.trim().to_lowercase().as_str()))
+        })
+        .filter_map(|n| n.attr("href"))
+        .filter_map(|href| page.join(href).ok())
+        .collect()
+}
+
+/// The item, entry or channel a node belongs to, and its title
+fn container<'a>(node: Node<'a, '_>) -> Option<(&'static str, String)> {
+    let container = node
+        .ancestors()
+        .find(|n| matches!(n.tag_name().name(), "item" | "entry" | "channel" | "feed"))?;
+    let element = match container.tag_name().name() {
+        "item" => "item",
+        "entry" => "entry",
+        "channel" => "channel",
+        _ => "feed",
+    };
+    let title = container
+        .children()
+        .find(|n| n.tag_name().name() == "title")
+        .and_then(|n| n.text())
+        .map(|title| title.split_whitespace().collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+    Some((element, title))
+}
+
+/// Item links, enclosures and atom links of an RSS 2.0, RSS 1.0 or Atom feed. The text of each
+/// link is the title of the item it belongs to
+fn get_raw_feed_links<'a>(feed: &'a XmlDocument) -> Vec<RawLink<'a>> {
+    let atom = feed.root_element().tag_name().name() == "feed";
+    feed.descendants()
+        .filter(|n| n.is_element())
+        .filter_map(|n| {
+            let (href, attribute) = match (n.tag_name().name(), n.attribute("href")) {
+                ("link", Some(href)) if atom => (href, "link"),
+                ("link", Some(href)) => (href, "atom:link"),
+                ("link", None) => (n.text()?, "link"),
+                ("comments", _) => (n.text()?, "comments"),
+                ("enclosure", _) => (n.attribute("url")?, "enclosure"),
+                ("content", _) if atom => (n.attribute("src")?, "content"),
+                _ => return None,
+            };
+            let (element, text) = container(n)?;
+            Some(RawLink {
+                href: href.trim(),
+                element,
+                attribute,
+                text,
+                rel: n.attribute("rel").into_iter().collect(),
+                descriptor: None,
+            })
+        })
+        .collect()
+}
+
+/// The links of a feed, or None if `xml` is not a feed
+pub fn get_links_from_feed(
+    xml: &str,
+    feed_url: &Url,
+    truncate_fragments: bool,
+    regex: &Option<Regex>,
+) -> Option<Vec<(Url, LinkSource)>> {
+    let feed = XmlDocument::parse(xml).ok()?;
+    if !matches!(
+        feed.root_element().tag_name().name(),
+        "rss" | "feed" | "RDF"
+    ) {
+        return None;
+    }
+    Some(resolve_links(
+        get_raw_feed_links(&feed),
+        feed_url,
+        None,
+        truncate_fragments,
+        regex,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use roxmltree::Document as XmlDocument;
+
+    use crate::feed::get_raw_feed_links;
+
+    #[test]
+    fn test_get_raw_feed_links() {
+        let rss = XmlDocument::parse(
+            r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel>
+                <title>Blog</title><link>https://blog.com/</link>
+                <atom:link href="https://blog.com/feed.xml" rel="self" type="application/rss+xml"/>
+                <item><title>First
+                  post</title><link> https://blog.com/first </link>
+                  <enclosure url="https://cdn.com/first.mp3" length="1" type="audio/mpeg"/>
+                  <comments>https://blog.com/first#comments</comments></item>
+            </channel></rss>"#,
+        )
+        .unwrap();
+        let links: Vec<(&str, &str, &str, String)> = get_raw_feed_links(&rss)
+            .into_iter()
+            .map(|l| (l.href, l.element, l.attribute, l.text))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                ("https://blog.com/", "channel", "link", "Blog".to_owned()),
+                (
+                    "https://blog.com/feed.xml",
+                    "channel",
+                    "atom:link",
+                    "Blog".to_owned()
+                ),
+                (
+                    "https://blog.com/first",
+                    "item",
+                    "link",
+                    "First post".to_owned()
+                ),
+                (
+                    "https://cdn.com/first.mp3",
+                    "item",
+                    "enclosure",
+                    "First post".to_owned()
+                ),
+                (
+                    "https://blog.com/first#comments",
+                    "item",
+                    "comments",
+                    "First post".to_owned()
+                ),
+            ]
+        );
+        let atom = XmlDocument::parse(
+            r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>Blog</title>
+                <link href="https://blog.com/"/>
+                <entry><title>Hi</title><link rel="alternate" href="/hi"/>
+                  <content type="video/mp4" src="/hi.mp4"/></entry></feed>"#,
+        )
+        .unwrap();
+        let links: Vec<(&str, &str, &str)> = get_raw_feed_links(&atom)
+            .into_iter()
+            .map(|l| (l.href, l.element, l.attribute))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                ("https://blog.com/", "feed", "link"),
+                ("/hi", "entry", "link"),
+                ("/hi.mp4", "entry", "content"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use http::StatusCode;
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+use crate::{
+    outcome::{CheckResult, Outcome},
+    req::run_blocking,
+};
+
+// Timeout for connecting and for every reply of the server
+const FTP_TIMEOUT: Duration = Duration::from_secs(10);
+const ANONYMOUS_USER: &str = "anonymous";
+const ANONYMOUS_PASSWORD: &str = "anonymous@";
+
+/// What we learned from an ftp server about a path
+#[derive(Debug, PartialEq)]
+enum Probe {
+    Found,
+    Missing,
+    /// The server answered something we didn't expect, e.g. it refused to log us in
+    Refused(String),
+}
+
+/// Reads a reply, which can span several lines, and returns its code and text
+fn read_reply(reader: &mut impl BufRead) -> io::Result<(u16, String)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let code: u16 = line
+        .get(..3)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, line.trim().to_owned()))?;
+    // A multiline reply goes on until a line that starts with the code and a space
+    if line.as_bytes().get(3) == Some(&b'-') {
+        let end = format!("{} ", code);
+        let mut next = String::new();
+        loop {
+            next.clear();
+            if reader.read_line(&mut next)? == 0 || next.starts_with(&end) {
+                break;
+            }
+        }
+        line = next;
+    }
+    Ok((code, line.trim().to_owned()))
+}
+
+/// The port of a `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)` reply. The address is ignored,
+/// as servers behind a NAT often get it wrong
+fn passive_port(reply: &str) -> Option<u16> {
+    let inside = reply.get(reply.find('(')? + 1..reply.rfind(')')?)?;
+    let numbers: Vec<u16> = inside
+        .split(',')
+        .map(|n| n.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match numbers[..] {
+        [_, _, _, _, high, low] if high < 256 && low < 256 => Some(high * 256 + low),
+        _ => None,
+    }
+}
+
+struct Control {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Control {
+    fn connect(address: SocketAddr) -> io::Result<Self> {
+        let stream = TcpStream::connect_timeout(&address, FTP_TIMEOUT)?;
+        stream.set_read_timeout(Some(FTP_TIMEOUT))?;
+        stream.set_write_timeout(Some(FTP_TIMEOUT))?;
+        Ok(Control {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+    fn command(&mut self, command: &str) -> io::Result<(u16, String)> {
+        self.writer
+            .write_all(format!("{}\r\n", command).as_bytes())?;
+        read_reply(&mut self.reader)
+    }
+    /// Lists `path` through a passive data connection, returning the final reply and whether
+    /// anything was listed
+    fn list(&mut self, path: &str) -> io::Result<(u16, bool)> {
+        let (code, reply) = self.command("PASV")?;
+        let port = match passive_port(&reply) {
+            Some(port) if code == 227 => port,
+            _ => return Ok((code, false)),
+        };
+        let address = SocketAddr::new(self.writer.peer_addr()?.ip(), port);
+        let mut data = TcpStream::connect_timeout(&address, FTP_TIMEOUT)?;
+        data.set_read_timeout(Some(FTP_TIMEOUT))?;
+        let (code, _) = self.command(&format!("LIST {}", path))?;
+        if code != 125 && code != 150 {
+            return Ok((code, false));
+        }
+        let mut listing = vec![];
+        data.read_to_end(&mut listing)?;
+        let (code, _) = read_reply(&mut self.reader)?;
+        Ok((code, listing.iter().any(|b| !b.is_ascii_whitespace())))
+    }
+}
+
+/// Logs in and asks for the size of the path, which only works for files, or else for a
+/// listing of it
+fn probe(address: SocketAddr, url: &Url) -> io::Result<Probe> {
+    let mut control = Control::connect(address)?;
+    let (code, reply) = read_reply(&mut control.reader)?;
+    if code != 220 {
+        return Ok(Probe::Refused(reply));
+    }
+    let user = match url.username() {
+        "" => ANONYMOUS_USER.to_owned(),
+        user => percent_decode_str(user).decode_utf8_lossy().into_owned(),
+    };
+    let password = url.password().map_or_else(
+        || ANONYMOUS_PASSWORD.to_owned(),
+        |password| {
+            percent_decode_str(password)
+                .decode_utf8_lossy()
+                .into_owned()
+        },
+    );
+    let (mut code, mut reply) = control.command(&format!("USER {}", user))?;
+    if code == 331 {
+        (code, reply) = control.command(&format!("PASS {}", password))?;
+    }
+    if code != 230 && code != 202 {
+        return Ok(Probe::Refused(reply));
+    }
+    // Paths can end with a ;type= parameter
+    let path = url.path().split(';').next().unwrap_or_default();
+    let path = percent_decode_str(path).decode_utf8_lossy().into_owned();
+    control.command("TYPE I")?;
+    if !path.ends_with('/') && control.command(&format!("SIZE {}", path))?.0 == 213 {
+        return Ok(Probe::Found);
+    }
+    let probe = match control.list(&path)? {
+        (226 | 250, listed) if listed || path.ends_with('/') => Probe::Found,
+        (226 | 250 | 450 | 550, _) => Probe::Missing,
+        (code, _) => Probe::Refused(format!("LIST answered {}", code)),
+    };
+    // We are done either way, the server can close the connection on its own
+    let _ = control.command("QUIT");
+    Ok(probe)
+}
+
+/// Connects to the ftp server of the url and checks that the file or directory exists
+pub async fn check_ftp_url(url: &Url) -> CheckResult {
+    let start = Instant::now();
+    let target = url.to_owned();
+    let outcome = run_blocking(move || {
+        let host = target.host_str().unwrap_or_default();
+        let port = target.port_or_known_default().unwrap_or(21);
+        let address = match (host, port).to_socket_addrs().map(|mut a| a.next()) {
+            Ok(Some(address)) => address,
+            _ => return Outcome::Unresolved,
+        };
+        match probe(address, &target) {
+            Ok(Probe::Found) => Outcome::Ok,
+            Ok(Probe::Missing) => Outcome::MissingFile,
+            Ok(Probe::Refused(reply)) => {
+                info!("[ERROR] Ftp server refused {}: {}", target, reply);
+                Outcome::FtpError
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                ) =>
+            {
+                Outcome::Timeout
+            }
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => Outcome::FtpError,
+            Err(_) => Outcome::ConnectionFailed,
+        }
+    })
+    .await
+    .unwrap_or(Outcome::ConnectionFailed);
+    let status = match outcome {
+        Outcome::Ok => StatusCode::OK,
+        Outcome::Timeout => StatusCode::REQUEST_TIMEOUT,
+        Outcome::FtpError => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::NOT_FOUND,
+    };
+    CheckResult {
+        elapsed: start.elapsed(),
+        ..CheckResult::new(url, status, outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::ftp::{passive_port, read_reply};
+
+    #[test]
+    fn test_ftp_replies() {
+        let mut replies =
+            Cursor::new("220-Welcome\r\n220-to the mirror\r\n220 Ready\r\n213 1024\r\ngarbage\r\n");
+        assert_eq!(
+            read_reply(&mut replies).unwrap(),
+            (220, "220 Ready".to_owned())
+        );
+        assert_eq!(
+            read_reply(&mut replies).unwrap(),
+            (213, "213 1024".to_owned())
+        );
+        assert!(read_reply(&mut replies).is_err());
+        assert_eq!(
+            passive_port("227 Entering Passive Mode (192,168,1,2,200,10)."),
+            Some(51210)
+        );
+        assert_eq!(passive_port("227 Entering Passive Mode (1,2,3)"), None);
+        assert_eq!(
+            passive_port("227 Entering Passive Mode )1,2,3,4,5,6("),
+            None
+        );
+        assert_eq!(passive_port("227 Entering Passive Mode ()"), None);
+    }
+}
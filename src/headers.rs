@@ -0,0 +1,143 @@
+use std::env;
+
+use http::{
+    header::{HeaderName, HeaderValue, AUTHORIZATION},
+    request::Builder,
+};
+use isahc::{
+    auth::{Authentication, Credentials},
+    config::{Configurable, RedirectPolicy},
+};
+use url::Url;
+
+use crate::{error::RLinksError, url_fix::host_matches};
+
+const ENV_PREFIX: &str = "env:";
+
+/// Values written as `env:NAME` are read from the environment variable NAME, so that secrets
+/// don't have to be written in the command line
+pub fn resolve_env(value: &str) -> Result<String, RLinksError> {
+    match value.strip_prefix(ENV_PREFIX) {
+        Some(name) => env::var(name).map_err(|_| RLinksError::MissingEnvVar(name.to_owned())),
+        None => Ok(value.to_owned()),
+    }
+}
+
+/// Parses a `Name: value` header
+pub fn parse_header(spec: &str) -> Result<(HeaderName, HeaderValue), RLinksError> {
+    let invalid = || RLinksError::HeaderParseError(spec.to_owned());
+    let (name, value) = spec.split_once(':').ok_or_else(invalid)?;
+    let name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| invalid())?;
+    let mut value = HeaderValue::from_str(&resolve_env(value.trim())?).map_err(|_| invalid())?;
+    value.set_sensitive(true);
+    Ok((name, value))
+}
+
+/// Headers and credentials that are only sent to some hosts
+#[derive(Debug, Clone, Default)]
+pub struct ScopedHeaders {
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    pub basic_auth: Option<(String, String)>,
+    /// Hosts the headers are sent to. Subdomains of these hosts are included
+    pub hosts: Vec<String>,
+}
+
+impl ScopedHeaders {
+    pub fn new(
+        headers: Vec<&str>,
+        basic_auth: Option<&str>,
+        bearer_token: Option<&str>,
+        hosts: Vec<String>,
+    ) -> Result<Self, RLinksError> {
+        let mut headers = headers
+            .into_iter()
+            .map(parse_header)
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(token) = bearer_token {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", resolve_env(token)?))
+                .map_err(|_| RLinksError::HeaderParseError("bearer token".to_owned()))?;
+            value.set_sensitive(true);
+            headers.push((AUTHORIZATION, value));
+        }
+        let basic_auth = match basic_auth.map(resolve_env).transpose()? {
+            Some(credentials) => {
+                let (user, password) = credentials
+                    .split_once(':')
+                    .ok_or_else(|| RLinksError::HeaderParseError("basic auth".to_owned()))?;
+                Some((user.to_owned(), password.to_owned()))
+            }
+            None => None,
+        };
+        Ok(ScopedHeaders {
+            headers,
+            basic_auth,
+            hosts,
+        })
+    }
+    fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.basic_auth.is_none()
+    }
+    /// Whether curl follows the redirects of a request to `url`, which it doesn't when the
+    /// request carries our headers
+    pub fn follows_redirects(&self, url: &Url) -> bool {
+        self.is_empty() || !self.applies_to(url)
+    }
+    pub fn applies_to(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host,
+            None => return false,
+        };
+        self.hosts.iter().any(|allowed| host_matches(host, allowed))
+    }
+    /// Adds the headers to a request if it is going to one of our hosts. curl would send them on
+    /// to wherever those requests redirect, so we follow their redirects ourselves
+    pub fn apply(&self, builder: Builder, url: &Url) -> Builder {
+        if self.follows_redirects(url) {
+            return builder;
+        }
+        let builder = self
+            .headers
+            .iter()
+            .fold(builder, |builder, (name, value)| {
+                builder.header(name, value)
+            })
+            .redirect_policy(RedirectPolicy::None);
+        match &self.basic_auth {
+            Some((user, password)) => builder
+                .authentication(Authentication::basic())
+                .credentials(Credentials::new(user.as_str(), password.as_str())),
+            None => builder,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::headers::{parse_header, ScopedHeaders};
+
+    #[test]
+    fn test_parse_header() {
+        let (name, value) = parse_header("X-Api-Key:  abc ").unwrap();
+        assert_eq!(name.as_str(), "x-api-key");
+        assert_eq!(value.to_str().unwrap(), "abc");
+        assert!(parse_header("no colon").is_err());
+        assert!(parse_header("X-Missing: env:RLINKS_SURELY_NOT_SET").is_err());
+    }
+
+    #[test]
+    fn test_applies_to() {
+        let headers = ScopedHeaders::new(
+            vec!["X-Token: abc"],
+            None,
+            None,
+            vec!["example.com".to_owned()],
+        )
+        .unwrap();
+        let applies = |url: &str| headers.applies_to(&Url::parse(url).unwrap());
+        assert!(applies("https://example.com/a"));
+        assert!(applies("https://docs.example.com/a"));
+        assert!(!applies("https://badexample.com/a"));
+    }
+}
@@ -0,0 +1,240 @@
+use regex::Regex;
+use serde_json::Value;
+use url::Url;
+
+use crate::{
+    error::RLinksError,
+    extract::RawLink,
+    req::{resolve_links, LinkSource},
+};
+
+/// One step of a selector
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    /// Every member of an object or array
+    Wildcard,
+    /// The value itself and everything under it, as in `..`
+    Descendants,
+}
+
+/// A JSONPath-like selector, such as `$.items[*].url` or `$..links`. Supports keys, array
+/// indices, `*` wildcards and `..` to look at any depth
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonSelector {
+    steps: Vec<Step>,
+}
+
+impl JsonSelector {
+    pub fn parse(selector: &str) -> Result<Self, RLinksError> {
+        let invalid = || RLinksError::SelectorParseError(selector.to_owned());
+        let mut rest = selector.trim();
+        rest = rest.strip_prefix('$').unwrap_or(rest);
+        let mut steps = vec![];
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("..") {
+                steps.push(Step::Descendants);
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('.') {
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(invalid)?;
+                let inside = after[..end].trim();
+                steps.push(match inside {
+                    "*" => Step::Wildcard,
+                    _ if inside.len() > 1
+                        && (inside.starts_with('\'') || inside.starts_with('"')) =>
+                    {
+                        Step::Key(inside[1..inside.len() - 1].to_owned())
+                    }
+                    _ => Step::Index(inside.parse().map_err(|_| invalid())?),
+                });
+                rest = &after[end + 1..];
+                continue;
+            } else if !steps.is_empty() {
+                return Err(invalid());
+            }
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            match &rest[..end] {
+                "" if steps.last() == Some(&Step::Descendants) => return Err(invalid()),
+                "" => (),
+                "*" => steps.push(Step::Wildcard),
+                key => steps.push(Step::Key(key.to_owned())),
+            }
+            rest = &rest[end..];
+        }
+        Ok(JsonSelector { steps })
+    }
+
+    /// The values the selector points to, along with their paths
+    fn select<'a>(&self, root: &'a Value) -> Vec<(String, &'a Value)> {
+        let mut selected = vec![("$".to_owned(), root)];
+        for step in &self.steps {
+            selected = selected
+                .into_iter()
+                .flat_map(|(path, value)| match step {
+                    Step::Key(key) => value
+                        .get(key)
+                        .map(|child| (key_path(&path, key), child))
+                        .into_iter()
+                        .collect(),
+                    Step::Index(i) => value
+                        .get(i)
+                        .map(|child| (format!("{}[{}]", path, i), child))
+                        .into_iter()
+                        .collect(),
+                    Step::Wildcard => children(&path, value),
+                    Step::Descendants => {
+                        let mut all = vec![];
+                        descendants(path, value, &mut all);
+                        all
+                    }
+                })
+                .collect();
+        }
+        selected
+    }
+}
+
+fn key_path(path: &str, key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        format!("{}.{}", path, key)
+    } else {
+        format!("{}['{}']", path, key)
+    }
+}
+
+fn children<'a>(path: &str, value: &'a Value) -> Vec<(String, &'a Value)> {
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, child)| (key_path(path, key), child))
+            .collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, child)| (format!("{}[{}]", path, i), child))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn descendants<'a>(path: String, value: &'a Value, found: &mut Vec<(String, &'a Value)>) {
+    let children = children(&path, value);
+    found.push((path, value));
+    for (path, child) in children {
+        descendants(path, child, found);
+    }
+}
+
+/// Every string under the selected values that parses as an absolute url, with its path
+fn json_urls<'a>(json: &'a Value, selector: Option<&JsonSelector>) -> Vec<(String, &'a str)> {
+    let selected = match selector {
+        Some(selector) => selector.select(json),
+        None => vec![("$".to_owned(), json)],
+    };
+    let mut values = vec![];
+    for (path, value) in selected {
+        descendants(path, value, &mut values);
+    }
+    values
+        .into_iter()
+        .filter_map(|(path, value)| match value {
+            Value::String(s) if Url::parse(s.trim()).is_ok_and(|url| url.has_host()) => {
+                Some((path, s.trim()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The links of a json document, such as an api response. Only the parts the selector points
+/// to are looked at, if there is one
+pub fn get_links_from_json(
+    text: &str,
+    page: &Url,
+    selector: Option<&JsonSelector>,
+    site_root: Option<&Url>,
+    truncate_fragments: bool,
+    regex: &Option<Regex>,
+) -> Result<Vec<(Url, LinkSource)>, RLinksError> {
+    let json: Value = serde_json::from_str(text)?;
+    let links = json_urls(&json, selector)
+        .into_iter()
+        .map(|(path, href)| RawLink {
+            href,
+            element: "json",
+            attribute: "string",
+            text: path,
+            rel: vec![],
+            descriptor: None,
+        })
+        .collect();
+    Ok(resolve_links(
+        links,
+        page,
+        site_root,
+        truncate_fragments,
+        regex,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::json_links::{json_urls, JsonSelector};
+
+    #[test]
+    fn test_json_urls() {
+        let json = json!({
+            "site": "https://cms.example.com",
+            "items": [
+                {"title": "a", "url": "https://example.com/a", "image": {"src": "https://cdn.example.com/a.png"}},
+                {"title": "not a url", "url": "/relative", "links": ["mailto:a@example.com", "http://b.com"]}
+            ],
+            "odd key": "https://example.com/odd"
+        });
+        let paths = |selector: Option<&str>| -> Vec<(String, &str)> {
+            let selector = selector.map(|s| JsonSelector::parse(s).unwrap());
+            let mut urls = json_urls(&json, selector.as_ref());
+            urls.sort();
+            urls
+        };
+        assert_eq!(
+            paths(None),
+            vec![
+                (
+                    "$.items[0].image.src".to_owned(),
+                    "https://cdn.example.com/a.png"
+                ),
+                ("$.items[0].url".to_owned(), "https://example.com/a"),
+                ("$.items[1].links[1]".to_owned(), "http://b.com"),
+                ("$.site".to_owned(), "https://cms.example.com"),
+                ("$['odd key']".to_owned(), "https://example.com/odd"),
+            ]
+        );
+        assert_eq!(
+            paths(Some("$.items[*].url")),
+            vec![("$.items[0].url".to_owned(), "https://example.com/a")]
+        );
+        assert_eq!(
+            paths(Some("$..src")),
+            vec![(
+                "$.items[0].image.src".to_owned(),
+                "https://cdn.example.com/a.png"
+            )]
+        );
+        assert_eq!(
+            paths(Some("items[1]")),
+            vec![("$.items[1].links[1]".to_owned(), "http://b.com")]
+        );
+        assert_eq!(
+            paths(Some("$['odd key']")),
+            vec![("$['odd key']".to_owned(), "https://example.com/odd")]
+        );
+        assert!(JsonSelector::parse("$.items[x]").is_err());
+        assert!(JsonSelector::parse("$..").is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use url::{Host, Url};
+
+use crate::text::ColorsExt;
+
+// Schemes that are one typo away from these are most likely typos
+const WEB_SCHEMES: [&str; 2] = ["http", "https"];
+
+/// How bad an href is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lint {
+    /// Browsers won't follow it as intended
+    Malformed,
+    /// It works, but probably shouldn't be there
+    Suspicious,
+}
+
+/// An href that was written wrong, and where
+#[derive(Debug, Clone, PartialEq)]
+pub struct HrefIssue<'a> {
+    pub page: &'a Url,
+    pub href: &'a str,
+    pub lint: Lint,
+    pub reason: String,
+}
+
+/// Number of single character edits that turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn is_local_host(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => {
+            domain.eq_ignore_ascii_case("localhost") || domain.ends_with(".localhost")
+        }
+        Some(Host::Ipv4(ip)) => ip.is_loopback() || ip.is_unspecified(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback() || ip.is_unspecified(),
+        None => false,
+    }
+}
+
+/// Whether an href is a windows path such as `C:\Users` or `file:///C:/Users`
+fn is_windows_path(href: &str) -> bool {
+    let path = href
+        .strip_prefix("file:")
+        .map_or(href, |path| path.trim_start_matches('/'))
+        .as_bytes();
+    path.len() > 2 && path[0].is_ascii_alphabetic() && path[1] == b':' && b"/\\".contains(&path[2])
+}
+
+/// Looks for common mistakes in an href. `url` is what it resolved to, or the error if it
+/// didn't resolve at all
+pub fn lint_href(href: &str, url: Result<&Url, String>, page: &Url) -> Option<(Lint, String)> {
+    let malformed = |reason: &str| Some((Lint::Malformed, reason.to_owned()));
+    let suspicious = |reason: &str| Some((Lint::Suspicious, reason.to_owned()));
+    let href = href.trim();
+    if href.is_empty() {
+        return malformed("empty href, it points back to the page itself");
+    }
+    if let Some((scheme, _)) = href.split_once(':') {
+        let scheme = scheme.to_ascii_lowercase();
+        if !WEB_SCHEMES.contains(&scheme.as_str())
+            && WEB_SCHEMES
+                .iter()
+                .any(|web| edit_distance(&scheme, web) == 1)
+        {
+            return malformed(&format!("misspelled scheme {}:", scheme));
+        }
+        if scheme == "javascript" {
+            return suspicious("javascript: pseudo-link");
+        }
+    }
+    let lower = href.to_ascii_lowercase();
+    if lower.starts_with("http//") || lower.starts_with("https//") {
+        return malformed("missing colon after the scheme");
+    }
+    let after_scheme = lower
+        .strip_prefix("http:")
+        .or_else(|| lower.strip_prefix("https:"));
+    if after_scheme.is_some_and(|rest| !rest.starts_with("//")) {
+        return malformed("missing slash after the scheme");
+    }
+    let url = match url {
+        Ok(url) => url,
+        Err(error) => return malformed(&error),
+    };
+    if is_windows_path(&lower) || (url.scheme() == "file" && page.scheme() != "file") {
+        return suspicious("links to a file on the author's disk");
+    }
+    if href.contains('\\') {
+        return malformed("backslash in the url");
+    }
+    if href.contains(char::is_whitespace) {
+        return malformed("whitespace in the url");
+    }
+    if is_local_host(url) && !is_local_host(page) {
+        return suspicious("links to localhost");
+    }
+    None
+}
+
+pub fn print_href_issues(issues: &[HrefIssue]) {
+    for issue in issues {
+        let message = format!(
+            "Bad link on {}: {:?} ({})",
+            issue.page, issue.href, issue.reason
+        );
+        match issue.lint {
+            Lint::Malformed => message.print_in_red(),
+            Lint::Suspicious => message.print_in_yellow(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::lint::{lint_href, Lint};
+
+    #[test]
+    fn test_lint_href() {
+        let page = Url::parse("https://example.com/docs/").unwrap();
+        let lint = |href: &str| {
+            let url = page.join(href).map_err(|e| e.to_string());
+            lint_href(href, url.as_ref().map_err(String::clone), &page).map(|(lint, _)| lint)
+        };
+        for fine in [
+            "https://example.com/a",
+            "/about",
+            "../intro.html",
+            "//cdn.example.com/a.js",
+            "mailto:a@example.com",
+            "ftp://mirror.example.com/pub/",
+            " https://example.com/padded ",
+        ] {
+            assert_eq!(lint(fine), None, "{}", fine);
+        }
+        for malformed in [
+            "",
+            "htp://example.com",
+            "htps://example.com",
+            "http//example.com",
+            "https:/example.com",
+            "https://example.com/a b",
+            "https:\\\\example.com\\a",
+            "http://[::1",
+        ] {
+            assert_eq!(lint(malformed), Some(Lint::Malformed), "{}", malformed);
+        }
+        for suspicious in [
+            "javascript:void(0)",
+            "http://localhost:3000/admin",
+            "http://127.0.0.1/",
+            "file:///C:/Users/me/report.pdf",
+            "C:\\Users\\me\\report.pdf",
+        ] {
+            assert_eq!(lint(suspicious), Some(Lint::Suspicious), "{}", suspicious);
+        }
+        let local = Url::parse("http://localhost:8000/").unwrap();
+        assert_eq!(
+            lint_href("http://localhost:8000/a", Ok(&local), &local),
+            None
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use http::StatusCode;
+use pulldown_cmark::{html, Parser};
+use regex::Regex;
+use select::document::Document;
+use url::Url;
+
+use crate::{
+    css::follow_stylesheets,
+    error::RLinksError,
+    json_links::{get_links_from_json, JsonSelector},
+    outcome::{CheckResult, Outcome},
+    pdf::{follow_pdfs, get_links_from_pdf},
+    plaintext::get_links_from_text,
+    req::{get_links_from_document, LinkSource, Links},
+};
+
+const HTML_EXTENSIONS: [&str; 2] = ["html", "htm"];
+const MARKDOWN_EXTENSIONS: [&str; 2] = ["md", "markdown"];
+const TEXT_EXTENSIONS: [&str; 2] = ["txt", "text"];
+
+/// Whether the target given in the command line is a local file or directory instead of a url
+pub fn is_local_path(target: &str) -> bool {
+    Path::new(target).exists()
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+}
+
+/// Only html, markdown, text and json files are looked at for links
+pub fn is_page(path: &Path) -> bool {
+    extension(path).is_some_and(|ext| {
+        let ext = ext.as_str();
+        HTML_EXTENSIONS.contains(&ext)
+            || MARKDOWN_EXTENSIONS.contains(&ext)
+            || TEXT_EXTENSIONS.contains(&ext)
+            || ext == "json"
+    })
+}
+
+fn is_markdown(path: &Path) -> bool {
+    extension(path).is_some_and(|ext| MARKDOWN_EXTENSIONS.contains(&ext.as_str()))
+}
+
+fn to_url(path: &Path) -> Result<Url, RLinksError> {
+    let path = path.canonicalize()?;
+    if path.is_dir() {
+        Url::from_directory_path(&path)
+    } else {
+        Url::from_file_path(&path)
+    }
+    .map_err(|_| RLinksError::LocalPathError(path))
+}
+
+/// The directory that root-relative links are resolved against
+pub fn site_root(target: &Path) -> Result<Url, RLinksError> {
+    match target.parent() {
+        Some(parent) if target.is_file() => to_url(parent),
+        _ => to_url(target),
+    }
+}
+
+/// All the pages of a local site, sorted
+pub fn site_files(target: &Path) -> Result<Vec<PathBuf>, RLinksError> {
+    if target.is_file() {
+        return Ok(vec![target.to_owned()]);
+    }
+    let mut files = vec![];
+    for entry in fs::read_dir(target)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(site_files(&path)?);
+        } else if is_page(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Extracts the links of a single html, markdown, pdf, json or text file. Markdown is rendered
+/// to html first
+pub fn get_links_from_file(
+    path: &Path,
+    site_root: &Url,
+    truncate_fragments: bool,
+    regex: &Option<Regex>,
+    json_selector: Option<&JsonSelector>,
+) -> Result<Vec<(Url, LinkSource)>, RLinksError> {
+    let page = to_url(path)?;
+    let site_root = Some(site_root);
+    let found = match extension(path).as_deref() {
+        Some("pdf") => get_links_from_pdf(
+            &fs::read(path)?,
+            &page,
+            site_root,
+            truncate_fragments,
+            regex,
+        )
+        .unwrap_or_default(),
+        Some("json") => get_links_from_json(
+            &fs::read_to_string(path)?,
+            &page,
+            json_selector,
+            site_root,
+            truncate_fragments,
+            regex,
+        )?,
+        Some(ext) if TEXT_EXTENSIONS.contains(&ext) => get_links_from_text(
+            &fs::read_to_string(path)?,
+            &page,
+            site_root,
+            truncate_fragments,
+            regex,
+        ),
+        _ if is_markdown(path) => {
+            let mut rendered = String::new();
+            html::push_html(&mut rendered, Parser::new(&fs::read_to_string(path)?));
+            get_links_from_document(
+                &Document::from(rendered.as_str()),
+                &page,
+                site_root,
+                truncate_fragments,
+                regex,
+            )
+        }
+        _ => get_links_from_document(
+            &Document::from(fs::read_to_string(path)?.as_str()),
+            &page,
+            site_root,
+            truncate_fragments,
+            regex,
+        ),
+    };
+    Ok(found)
+}
+
+/// Returns the links of a page in a local site, and of the local stylesheets and pdf documents
+/// it uses
+pub async fn get_links_from_local_page(
+    path: &Path,
+    site_root: &Url,
+    truncate_fragments: bool,
+    regex: &Option<Regex>,
+    json_selector: Option<&JsonSelector>,
+) -> Result<Vec<(Url, LinkSource)>, RLinksError> {
+    let mut found = get_links_from_file(path, site_root, truncate_fragments, regex, json_selector)?;
+    let fetch = |url: Url| async move {
+        let path = url.to_file_path().ok()?;
+        fs::read_to_string(path).ok()
+    };
+    found.extend(
+        follow_stylesheets(&found, fetch, Some(site_root), truncate_fragments, regex).await,
+    );
+    let fetch = |url: Url| async move { fs::read(url.to_file_path().ok()?).ok() };
+    // Reading a file doesn't wait on anything, so there is nothing to gain from concurrency
+    found.extend(follow_pdfs(&found, fetch, 1, Some(site_root), truncate_fragments, regex).await);
+    Ok(found)
+}
+
+/// Returns the links of every page in a local site, and of the local stylesheets and pdf
+/// documents they use
+pub async fn get_links_from_site(
+    target: &Path,
+    truncate_fragments: bool,
+    regex: &Option<Regex>,
+    json_selector: Option<&JsonSelector>,
+) -> Result<Links, RLinksError> {
+    let site_root = site_root(target)?;
+    let mut found = vec![];
+    for path in site_files(target)? {
+        found.extend(
+            get_links_from_local_page(&path, &site_root, truncate_fragments, regex, json_selector)
+                .await?,
+        );
+    }
+    Ok(Links::from_found(found))
+}
+
+/// A file url is fine if it points to an existing file, or to a directory with an index page
+pub fn check_file_url(url: &Url) -> CheckResult {
+    let exists = url.to_file_path().is_ok_and(|path| {
+        if path.is_dir() {
+            HTML_EXTENSIONS
+                .iter()
+                .chain(MARKDOWN_EXTENSIONS.iter())
+                .any(|ext| path.join(format!("index.{}", ext)).is_file())
+        } else {
+            path.is_file()
+        }
+    });
+    let (status, outcome) = if exists {
+        (StatusCode::OK, Outcome::Ok)
+    } else {
+        (StatusCode::NOT_FOUND, Outcome::MissingFile)
+    };
+    CheckResult::new(url, status, outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use futures::executor::block_on;
+    use url::Url;
+
+    use crate::local::{get_links_from_local_page, site_root};
+
+    #[test]
+    fn test_get_links_from_local_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let page = dir.path().join("index.html");
+        fs::write(
+            &page,
+            r#"<link rel="stylesheet" href="style.css"><a href="https://example.com/">x</a>"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("style.css"),
+            "body { background: url(bg.png) }",
+        )
+        .unwrap();
+        let root = site_root(dir.path()).unwrap();
+        let found = block_on(get_links_from_local_page(&page, &root, true, &None, None)).unwrap();
+        let urls: Vec<&Url> = found.iter().map(|(url, _)| url).collect();
+        assert_eq!(urls.len(), 3);
+        assert!(urls.contains(&&root.join("style.css").unwrap()));
+        assert!(urls.contains(&&root.join("bg.png").unwrap()));
+    }
+}
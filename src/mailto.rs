@@ -0,0 +1,249 @@
+use std::sync::OnceLock;
+
+use hickory_resolver::{error::ResolveErrorKind, proto::op::ResponseCode, Resolver};
+use http::StatusCode;
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+use crate::{
+    outcome::{CheckResult, Outcome},
+    req::run_blocking,
+};
+
+// Limits on the parts of an address that can actually be delivered to, from RFC 5321
+const MAX_LOCAL_PART: usize = 64;
+const MAX_DOMAIN: usize = 255;
+// Headers of a mailto url that hold more recipients
+const RECIPIENT_HEADERS: [&str; 3] = ["to", "cc", "bcc"];
+
+static RESOLVER: OnceLock<Option<Resolver>> = OnceLock::new();
+
+/// Every recipient of a mailto url, from its path and its to, cc and bcc headers
+fn mailto_addresses(url: &Url) -> Vec<String> {
+    let headers = url
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            RECIPIENT_HEADERS
+                .iter()
+                .any(|header| name.eq_ignore_ascii_case(header))
+                .then_some(value)
+        });
+    std::iter::once(url.path())
+        .chain(headers)
+        .flat_map(|addresses| addresses.split(','))
+        .map(|address| {
+            percent_decode_str(address)
+                .decode_utf8_lossy()
+                .trim()
+                .to_owned()
+        })
+        .filter(|address| !address.is_empty())
+        .collect()
+}
+
+/// Characters allowed in an atom besides letters and digits. Non ascii letters are allowed too,
+/// as in internationalized addresses
+fn is_atext(c: char) -> bool {
+    c.is_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c)
+}
+
+/// Problem with a dot-atom, such as the local part or the domain of an address
+fn dot_atom_problem(atom: &str, part: &str) -> Option<String> {
+    if let Some(c) = atom.chars().find(|c| *c != '.' && !is_atext(*c)) {
+        return Some(format!("invalid character {:?} in the {}", c, part));
+    }
+    if atom.starts_with('.') || atom.ends_with('.') {
+        return Some(format!("the {} starts or ends with a dot", part));
+    }
+    if atom.contains("..") {
+        return Some(format!("consecutive dots in the {}", part));
+    }
+    None
+}
+
+/// Splits a quoted local part, such as `"john doe"@example.com`, from the rest of the address
+fn split_quoted(address: &str) -> Option<(&str, &str)> {
+    let mut escaped = false;
+    for (i, c) in address.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(address.split_at(i + 1)),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// What makes an address invalid according to the addr-spec grammar of RFC 5322, if anything.
+/// Comments and the obsolete forms of the grammar are not accepted
+fn address_problem(address: &str) -> Option<String> {
+    let (local, rest) = if address.starts_with('"') {
+        match split_quoted(address) {
+            Some(split) => split,
+            None => return Some("unbalanced quotes in the local part".to_owned()),
+        }
+    } else {
+        match address.find('@') {
+            Some(i) => address.split_at(i),
+            None => return Some("missing @".to_owned()),
+        }
+    };
+    let domain = match rest.strip_prefix('@') {
+        Some(domain) => domain,
+        None => return Some("missing @ after the quoted local part".to_owned()),
+    };
+    if local.is_empty() {
+        return Some("empty local part".to_owned());
+    }
+    if domain.is_empty() {
+        return Some("empty domain".to_owned());
+    }
+    if local.len() > MAX_LOCAL_PART {
+        return Some(format!(
+            "local part longer than {} characters",
+            MAX_LOCAL_PART
+        ));
+    }
+    if domain.len() > MAX_DOMAIN {
+        return Some(format!("domain longer than {} characters", MAX_DOMAIN));
+    }
+    if !local.starts_with('"') {
+        if let Some(problem) = dot_atom_problem(local, "local part") {
+            return Some(problem);
+        }
+    }
+    match domain.strip_prefix('[') {
+        Some(literal) => {
+            let valid = literal.strip_suffix(']').is_some_and(|inside| {
+                inside
+                    .chars()
+                    .all(|c| c.is_ascii_graphic() && !"[]\\".contains(c))
+            });
+            (!valid).then(|| "invalid domain literal".to_owned())
+        }
+        None => dot_atom_problem(domain, "domain"),
+    }
+}
+
+/// Whether a domain can receive mail: it has MX records or, failing that, an address. A lookup
+/// that fails for any other reason than the records not existing gives the domain the benefit
+/// of the doubt
+fn domain_exists(domain: &str) -> bool {
+    let resolver = match RESOLVER.get_or_init(|| Resolver::from_system_conf().ok()) {
+        Some(resolver) => resolver,
+        None => return true,
+    };
+    // Only an answer from the name server says the records don't exist, failures don't
+    let missing = |kind: &ResolveErrorKind| {
+        matches!(
+            kind,
+            ResolveErrorKind::NoRecordsFound {
+                response_code: ResponseCode::NXDomain | ResponseCode::NoError,
+                ..
+            }
+        )
+    };
+    // A trailing dot keeps the search domains of the system out of it
+    let fqdn = format!("{}.", domain.trim_end_matches('.'));
+    match resolver.mx_lookup(fqdn.as_str()) {
+        Ok(records) if records.iter().next().is_some() => true,
+        Err(e) if !missing(e.kind()) => true,
+        _ => match resolver.lookup_ip(fqdn.as_str()) {
+            Ok(addresses) => addresses.iter().next().is_some(),
+            Err(e) => !missing(e.kind()),
+        },
+    }
+}
+
+async fn domain_exists_in_thread(domain: &str) -> bool {
+    let domain = domain.to_owned();
+    run_blocking(move || domain_exists(&domain))
+        .await
+        .unwrap_or(true)
+}
+
+/// Checks the syntax of every address in a mailto url and, if asked to, that their domains
+/// exist. Returns the problems found along with the result
+pub async fn check_mailto_url(url: &Url, check_domains: bool) -> (CheckResult, Vec<String>) {
+    let addresses = mailto_addresses(url);
+    let mut problems: Vec<String> = addresses
+        .iter()
+        .filter_map(|address| {
+            address_problem(address).map(|problem| format!("{} ({})", address, problem))
+        })
+        .collect();
+    let mut outcome = if problems.is_empty() {
+        Outcome::Ok
+    } else {
+        Outcome::InvalidAddress
+    };
+    if check_domains && outcome.is_ok() {
+        for address in addresses {
+            let domain = address.rsplit('@').next().unwrap_or_default();
+            if !domain.starts_with('[') && !domain_exists_in_thread(domain).await {
+                problems.push(format!("{} (no MX or A records for {})", address, domain));
+                outcome = Outcome::MailDomainNotFound;
+            }
+        }
+    }
+    let status = match outcome {
+        Outcome::Ok => StatusCode::OK,
+        Outcome::InvalidAddress => StatusCode::BAD_REQUEST,
+        _ => StatusCode::NOT_FOUND,
+    };
+    let result = CheckResult::new(url, status, outcome);
+    (result, problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::mailto::{address_problem, mailto_addresses};
+
+    #[test]
+    fn test_mailto_addresses() {
+        let url =
+            Url::parse("mailto:a@example.com,%20b%2Btag@example.com?subject=hi&CC=c@example.com&body=d@example.com")
+                .unwrap();
+        assert_eq!(
+            mailto_addresses(&url),
+            vec!["a@example.com", "b+tag@example.com", "c@example.com"]
+        );
+        assert!(mailto_addresses(&Url::parse("mailto:?subject=hi").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_address_problem() {
+        for valid in [
+            "john.doe@example.com",
+            "o'brien+news@sub.example.co.uk",
+            "\"john doe\"@example.com",
+            "\"a@b\"@example.com",
+            "user@[192.168.0.1]",
+            "user@localhost",
+            "jörg@müller.de",
+        ] {
+            assert_eq!(address_problem(valid), None, "{}", valid);
+        }
+        for invalid in [
+            "john.example.com",
+            "john@@example.com",
+            "john..doe@example.com",
+            ".john@example.com",
+            "john@example.com.",
+            "john doe@example.com",
+            "john@exa mple.com",
+            "@example.com",
+            "john@",
+            "\"john@example.com",
+            "user@[1.2.3.4",
+        ] {
+            assert!(address_problem(invalid).is_some(), "{}", invalid);
+        }
+    }
+}
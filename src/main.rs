@@ -7,14 +7,41 @@ extern crate log;
 use crate::{
     cli::{get_config, make_app, CommandConfig},
     error::RLinksError,
+    text::ColorsExt,
 };
 use clap::App;
 
+mod baseline;
 mod cli;
 mod commands;
+mod cookies;
+mod css;
+mod data_url;
 mod error;
+mod extract;
+mod feed;
+mod ftp;
+mod headers;
+mod json_links;
+mod lint;
+mod local;
+mod mailto;
+mod metadata;
+mod mixed;
+mod outcome;
+mod pdf;
+mod plaintext;
+mod proxy;
+mod render;
+mod report;
 mod req;
+mod resource;
+mod soft404;
+mod status;
+#[cfg(test)]
+mod test_support;
 mod text;
+mod tls;
 mod url_fix;
 
 async fn run_app<'a, 'b>(app: App<'a, 'b>) -> Result<(), RLinksError> {
@@ -31,6 +58,7 @@ fn main() {
     let app = make_app();
     let result = futures::executor::block_on(run_app(app));
     if let Err(e) = result {
-        println!("{}", e);
+        format!("{}", e).print_in_red();
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,156 @@
+use std::collections::{HashMap, HashSet};
+
+use http::StatusCode;
+use url::Url;
+
+use crate::{
+    outcome::CheckResult,
+    req::{LinkSource, Links},
+    text::ColorsExt,
+};
+
+fn has_rel(source: &LinkSource, rel: &str) -> bool {
+    source.element == "link" && source.rel.iter().any(|r| r.eq_ignore_ascii_case(rel))
+}
+
+/// A metadata rule a page breaks
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataIssue<'a> {
+    pub page: &'a Url,
+    pub url: &'a Url,
+    pub problem: String,
+}
+
+/// Checks the rules search engines set for metadata links: a page has a single canonical url,
+/// written as an absolute url, that answers 200 without redirecting, and alternate language
+/// versions link back to the pages that list them. Alternates can only be checked for pages we
+/// crawled
+pub fn check_metadata<'a>(links: &'a Links, results: &'a [CheckResult]) -> Vec<MetadataIssue<'a>> {
+    let results: HashMap<&Url, &CheckResult> =
+        results.iter().map(|result| (&result.url, result)).collect();
+    let mut issues = vec![];
+    let mut canonicals: HashMap<&Url, Vec<&Url>> = HashMap::new();
+    // Every (page, alternate) pair, to look for the way back
+    let mut alternates: HashSet<(&Url, &Url)> = HashSet::new();
+    let crawled: HashSet<&Url> = links
+        .sources
+        .values()
+        .flatten()
+        .map(|source| &source.page)
+        .collect();
+    for (url, sources) in &links.sources {
+        for source in sources {
+            let page = &source.page;
+            let mut issue = |problem: String| {
+                issues.push(MetadataIssue { page, url, problem });
+            };
+            if has_rel(source, "canonical") {
+                canonicals.entry(page).or_default().push(url);
+                if Url::parse(&source.href).is_err() {
+                    issue(format!("canonical url {} is not absolute", source.href));
+                }
+                match results.get(url) {
+                    Some(result) if result.redirect.is_some() => {
+                        issue("canonical url redirects".to_owned())
+                    }
+                    Some(result) if result.status != StatusCode::OK => {
+                        issue(format!("canonical url answers {}", result.status))
+                    }
+                    _ => (),
+                }
+            }
+            if has_rel(source, "alternate") && !source.text.is_empty() {
+                alternates.insert((page, url));
+            }
+        }
+    }
+    for (page, urls) in canonicals {
+        if urls.len() > 1 {
+            issues.push(MetadataIssue {
+                page,
+                url: urls[0],
+                problem: format!("{} canonical urls", urls.len()),
+            });
+        }
+    }
+    for (page, alternate) in &alternates {
+        if page != alternate
+            && crawled.contains(alternate)
+            && !alternates.contains(&(*alternate, *page))
+        {
+            issues.push(MetadataIssue {
+                page,
+                url: alternate,
+                problem: "hreflang alternate doesn't link back".to_owned(),
+            });
+        }
+    }
+    issues.sort_by(|a, b| (a.page, a.url).cmp(&(b.page, b.url)));
+    issues
+}
+
+pub fn print_metadata_issues(issues: &[MetadataIssue]) {
+    for issue in issues {
+        format!(
+            "Metadata of {}: {} ({})",
+            issue.page, issue.problem, issue.url
+        )
+        .print_in_red();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use url::Url;
+
+    use crate::{
+        metadata::check_metadata,
+        outcome::Outcome,
+        req::{LinkSource, Links},
+        test_support::{found, result},
+    };
+
+    fn link(page: &str, href: &str, rel: &str, text: &str) -> (Url, LinkSource) {
+        let (url, source) = found(&Url::parse(page).unwrap(), href, "link", &[rel]);
+        let text = text.to_owned();
+        (url, LinkSource { text, ..source })
+    }
+
+    #[test]
+    fn test_check_metadata() {
+        let links = Links::from_found(vec![
+            link("https://a.com/en/", "/en/", "canonical", ""),
+            link("https://a.com/en/", "https://a.com/de/", "alternate", "de"),
+            link("https://a.com/en/", "https://a.com/fr/", "alternate", "fr"),
+            link("https://a.com/de/", "https://a.com/de/", "canonical", ""),
+            link("https://a.com/fr/", "https://a.com/en/", "alternate", "en"),
+        ]);
+        let results = vec![result(
+            "https://a.com/de/",
+            StatusCode::NOT_FOUND,
+            Outcome::HttpError,
+        )];
+        let problems: Vec<(&str, String)> = check_metadata(&links, &results)
+            .into_iter()
+            .map(|issue| (issue.page.as_str(), issue.problem))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                (
+                    "https://a.com/de/",
+                    "canonical url answers 404 Not Found".to_owned()
+                ),
+                (
+                    "https://a.com/en/",
+                    "hreflang alternate doesn't link back".to_owned()
+                ),
+                (
+                    "https://a.com/en/",
+                    "canonical url /en/ is not absolute".to_owned()
+                ),
+            ]
+        );
+    }
+}
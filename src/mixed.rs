@@ -0,0 +1,234 @@
+use futures::{stream, StreamExt};
+use isahc::HttpClient;
+use url::Url;
+
+use crate::{
+    cli::RequestConfig,
+    outcome::{CheckResult, Outcome},
+    req::{head_or_get, LinkSource, Links},
+    text::ColorsExt,
+};
+
+/// Browsers block active mixed content, and only warn about passive mixed content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MixedContent {
+    Active,
+    Passive,
+}
+
+impl MixedContent {
+    /// Whether loading `url` from where `source` found it is mixed content. Plain links are
+    /// not, as following them leaves the page
+    pub fn of(url: &Url, source: &LinkSource) -> Option<Self> {
+        if source.page.scheme() != "https" || url.scheme() != "http" {
+            return None;
+        }
+        match source.element.as_str() {
+            // Whatever element has the style attribute, its urls are backgrounds and the like
+            _ if source.attribute == "style" => Some(MixedContent::Passive),
+            "img" | "source" => Some(MixedContent::Passive),
+            "script" | "iframe" => Some(MixedContent::Active),
+            "css" | "style" if source.attribute == "@import" => Some(MixedContent::Active),
+            // Images and fonts from `url()`
+            "css" | "style" => Some(MixedContent::Passive),
+            "link" if source.attribute == "imagesrcset" => Some(MixedContent::Passive),
+            "link"
+                if source
+                    .rel
+                    .iter()
+                    .any(|rel| rel.eq_ignore_ascii_case("stylesheet")) =>
+            {
+                Some(MixedContent::Active)
+            }
+            _ => None,
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            MixedContent::Active => "active",
+            MixedContent::Passive => "passive",
+        }
+    }
+}
+
+/// An http resource loaded by an https page
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixedResource<'a> {
+    pub page: &'a Url,
+    pub url: &'a Url,
+    pub kind: MixedContent,
+    /// Whether the same resource answered over https, if we asked
+    pub https_available: Option<bool>,
+}
+
+/// Every http resource loaded by an https page, active ones first
+pub fn find_mixed_content(links: &Links) -> Vec<MixedResource<'_>> {
+    let mut found: Vec<MixedResource> = links
+        .sources
+        .iter()
+        .flat_map(|(url, sources)| {
+            sources.iter().filter_map(move |source| {
+                MixedContent::of(url, source).map(|kind| MixedResource {
+                    page: &source.page,
+                    url,
+                    kind,
+                    https_available: None,
+                })
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| {
+        (a.kind, a.page.as_str(), a.url.as_str()).cmp(&(b.kind, b.page.as_str(), b.url.as_str()))
+    });
+    found.dedup();
+    found
+}
+
+/// The same url over https. A url on a port of its own is left alone, as the server on that port
+/// almost never speaks tls too
+pub fn https_version(url: &Url) -> Option<Url> {
+    if url.port().is_some() {
+        return None;
+    }
+    let mut https = url.clone();
+    https.set_scheme("https").ok()?;
+    Some(https)
+}
+
+/// Whether `url` can be reached over https too
+pub async fn is_available_over_https(
+    client: &HttpClient,
+    request: &RequestConfig,
+    url: &Url,
+) -> bool {
+    let https = match https_version(url) {
+        Some(https) => https,
+        None => return false,
+    };
+    let response = head_or_get(client, request, &https).await;
+    // Failures come back as fake responses that carry their outcome
+    response.extensions().get::<Outcome>().is_none()
+        && request.status.accepts(&https, response.status())
+}
+
+/// Asks for every mixed resource over https, to tell whether its link can simply be upgraded
+pub async fn probe_https(
+    mixed: &mut [MixedResource<'_>],
+    client: &HttpClient,
+    request: &RequestConfig,
+    n_par: usize,
+) {
+    stream::iter(mixed.iter_mut())
+        .for_each_concurrent(n_par, |resource| async move {
+            resource.https_available =
+                Some(is_available_over_https(client, request, resource.url).await);
+        })
+        .await;
+}
+
+/// Looks for an https replacement for every working http link. A link that already redirects
+/// to its https version doesn't need to be asked again
+pub async fn suggest_https_upgrades(
+    results: &mut [CheckResult],
+    client: &HttpClient,
+    request: &RequestConfig,
+    n_par: usize,
+) {
+    let candidates = results
+        .iter_mut()
+        .filter(|result| result.url.scheme() == "http" && result.outcome.is_ok());
+    stream::iter(candidates)
+        .for_each_concurrent(n_par, |result| async move {
+            let https = match https_version(&result.url) {
+                Some(https) => https,
+                None => return,
+            };
+            if result.redirect.as_ref() == Some(&https)
+                || is_available_over_https(client, request, &result.url).await
+            {
+                format!("Upgradeable to https: {} -> {}", result.url, https).print_in_green();
+                result.https_upgrade = Some(https);
+            }
+        })
+        .await;
+}
+
+pub fn print_mixed_content(mixed: &[MixedResource]) {
+    for resource in mixed {
+        let upgrade = match resource.https_available {
+            Some(true) => ", also available over https",
+            Some(false) => ", not available over https",
+            None => "",
+        };
+        let message = format!(
+            "Mixed content ({}) on {}: {}{}",
+            resource.kind.label(),
+            resource.page,
+            resource.url,
+            upgrade
+        );
+        match resource.kind {
+            MixedContent::Active => message.print_in_red(),
+            MixedContent::Passive => message.print_in_yellow(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::{
+        mixed::{https_version, MixedContent},
+        req::LinkSource,
+        test_support,
+    };
+
+    fn source(page: &str, element: &str, rel: &[&str]) -> LinkSource {
+        test_support::source(&Url::parse(page).unwrap(), "", element, rel)
+    }
+
+    #[test]
+    fn test_mixed_content() {
+        let http = Url::parse("http://cdn.example.com:80/a.js").unwrap();
+        let secure = "https://example.com/";
+        assert_eq!(
+            MixedContent::of(&http, &source(secure, "script", &[])),
+            Some(MixedContent::Active)
+        );
+        assert_eq!(
+            MixedContent::of(&http, &source(secure, "link", &["stylesheet"])),
+            Some(MixedContent::Active)
+        );
+        assert_eq!(
+            MixedContent::of(&http, &source(secure, "img", &[])),
+            Some(MixedContent::Passive)
+        );
+        let css = |element, attribute| LinkSource {
+            attribute,
+            ..source(secure, element, &[])
+        };
+        assert_eq!(
+            MixedContent::of(&http, &css("css", "@import")),
+            Some(MixedContent::Active)
+        );
+        assert_eq!(
+            MixedContent::of(&http, &css("css", "url")),
+            Some(MixedContent::Passive)
+        );
+        assert_eq!(
+            MixedContent::of(&http, &css("div", "style")),
+            Some(MixedContent::Passive)
+        );
+        assert_eq!(MixedContent::of(&http, &source(secure, "a", &[])), None);
+        assert_eq!(
+            MixedContent::of(&http, &source("http://example.com/", "script", &[])),
+            None
+        );
+        assert_eq!(
+            https_version(&http).unwrap().as_str(),
+            "https://cdn.example.com/a.js"
+        );
+        assert!(https_version(&Url::parse("http://example.com:8080/a").unwrap()).is_none());
+    }
+}
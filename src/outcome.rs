@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use http::StatusCode;
+use isahc::{prelude::*, AsyncBody, Response};
+use serde::Serialize;
+use url::Url;
+
+/// Broad category a checked link falls into. Failures that never produced a real response
+/// (timeouts, DNS errors...) are attached to the fake responses built in `req`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Ok,
+    HttpError,
+    Timeout,
+    Unresolved,
+    ConnectionFailed,
+    TooManyRedirects,
+    BodyError,
+    MissingFile,
+    ProxyError,
+    CertificateExpired,
+    CertificateSelfSigned,
+    CertificateHostMismatch,
+    CertificateUntrusted,
+    TlsError,
+    ClientCertificateError,
+    SuspectedSoft404,
+    WrongContentType,
+    TooLarge,
+    InvalidAddress,
+    MailDomainNotFound,
+    InvalidData,
+    FtpError,
+}
+
+impl Outcome {
+    pub fn is_ok(self) -> bool {
+        self == Outcome::Ok
+    }
+    /// Something on our side failed, so we can't tell whether the link works
+    pub fn is_unchecked(self) -> bool {
+        matches!(self, Outcome::ProxyError | Outcome::ClientCertificateError)
+    }
+    /// The link answered, but with something that looks wrong
+    pub fn is_suspect(self) -> bool {
+        matches!(self, Outcome::SuspectedSoft404 | Outcome::TooLarge)
+    }
+    pub fn is_broken(self) -> bool {
+        !self.is_ok() && !self.is_unchecked() && !self.is_suspect()
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            Outcome::Ok => "ok",
+            Outcome::HttpError => "http error",
+            Outcome::Timeout => "timeout",
+            Outcome::Unresolved => "unresolved host",
+            Outcome::ConnectionFailed => "connection failed",
+            Outcome::TooManyRedirects => "too many redirects",
+            Outcome::BodyError => "body error",
+            Outcome::MissingFile => "missing file",
+            Outcome::ProxyError => "proxy error",
+            Outcome::CertificateExpired => "certificate expired",
+            Outcome::CertificateSelfSigned => "self-signed certificate",
+            Outcome::CertificateHostMismatch => "certificate hostname mismatch",
+            Outcome::CertificateUntrusted => "untrusted certificate",
+            Outcome::TlsError => "tls error",
+            Outcome::ClientCertificateError => "client certificate error",
+            Outcome::SuspectedSoft404 => "suspected soft 404",
+            Outcome::WrongContentType => "wrong content type",
+            Outcome::TooLarge => "too large",
+            Outcome::InvalidAddress => "invalid address",
+            Outcome::MailDomainNotFound => "mail domain not found",
+            Outcome::InvalidData => "invalid data url",
+            Outcome::FtpError => "ftp error",
+        }
+    }
+}
+
+/// Everything we learned about a single url. Both the terminal output and the reports are
+/// derived from this
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub url: Url,
+    pub status: StatusCode,
+    pub outcome: Outcome,
+    pub redirect: Option<Url>,
+    pub elapsed: Duration,
+    /// The https url an http link can be replaced with, if we looked for one
+    pub https_upgrade: Option<Url>,
+}
+
+impl CheckResult {
+    /// A result we didn't get by following redirects or timing a request
+    pub fn new(url: &Url, status: StatusCode, outcome: Outcome) -> Self {
+        CheckResult {
+            url: url.to_owned(),
+            status,
+            outcome,
+            redirect: None,
+            elapsed: Duration::default(),
+            https_upgrade: None,
+        }
+    }
+    /// `accepted` is whether the status code counts as success for this url
+    pub fn from_response(
+        url: &Url,
+        response: &Response<AsyncBody>,
+        elapsed: Duration,
+        accepted: bool,
+    ) -> Self {
+        let status = response.status();
+        let outcome = match response.extensions().get::<Outcome>() {
+            Some(outcome) => *outcome,
+            None if accepted => Outcome::Ok,
+            None => Outcome::HttpError,
+        };
+        let redirect = response
+            .effective_uri()
+            .and_then(|uri| Url::parse(&uri.to_string()).ok())
+            .filter(|effective| effective != url);
+        CheckResult {
+            redirect,
+            elapsed,
+            ..CheckResult::new(url, status, outcome)
+        }
+    }
+}
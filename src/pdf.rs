@@ -0,0 +1,249 @@
+use std::{collections::HashSet, future::Future};
+
+use futures::{stream, StreamExt};
+use lopdf::{Dictionary, Document as PdfDocument};
+use regex::Regex;
+use url::Url;
+
+use crate::{
+    extract::RawLink,
+    req::{resolve_links, LinkSource},
+};
+
+/// Whether `url` looks like it points to a pdf document
+pub fn is_pdf_url(url: &Url) -> bool {
+    url.path().to_lowercase().ends_with(".pdf")
+}
+
+/// The target of a link annotation, if it opens a uri
+fn annotation_uri(pdf: &PdfDocument, annotation: &Dictionary) -> Option<String> {
+    if annotation.get(b"Subtype").and_then(|s| s.as_name()).ok()? != b"Link" {
+        return None;
+    }
+    let action = annotation
+        .get_deref(b"A", pdf)
+        .and_then(|a| a.as_dict())
+        .ok()?;
+    if action.get(b"S").and_then(|s| s.as_name()).ok()? != b"URI" {
+        return None;
+    }
+    let uri = action
+        .get_deref(b"URI", pdf)
+        .and_then(|u| u.as_str())
+        .ok()?;
+    Some(String::from_utf8_lossy(uri).trim().to_owned())
+}
+
+/// The uri of every link annotation in a pdf, along with the page number it is on
+fn pdf_uris(bytes: &[u8]) -> Option<Vec<(u32, String)>> {
+    let pdf = PdfDocument::load_mem(bytes).ok()?;
+    let mut uris = vec![];
+    for (number, page) in pdf.get_pages() {
+        let annotations = pdf.get_page_annotations(page).unwrap_or_default();
+        uris.extend(
+            annotations
+                .into_iter()
+                .filter_map(|annotation| annotation_uri(&pdf, annotation))
+                .map(|uri| (number, uri)),
+        );
+    }
+    Some(uris)
+}
+
+/// The links of a pdf document, or None if `bytes` is not one. Each link is reported as found
+/// in `pdf_url#page=N`, the way pdf viewers address a single page
+pub fn get_links_from_pdf(
+    bytes: &[u8],
+    pdf_url: &Url,
+    site_root: Option<&Url>,
+    truncate_fragments: bool,
+    regex: &Option<Regex>,
+) -> Option<Vec<(Url, LinkSource)>> {
+    let uris = pdf_uris(bytes)?;
+    let mut found = vec![];
+    let mut pages: Vec<u32> = uris.iter().map(|(number, _)| *number).collect();
+    pages.dedup();
+    for number in pages {
+        let mut page = pdf_url.clone();
+        page.set_fragment(Some(&format!("page={}", number)));
+        let links = uris
+            .iter()
+            .filter(|(n, _)| *n == number)
+            .map(|(_, uri)| RawLink {
+                href: uri,
+                element: "pdf",
+                attribute: "uri",
+                text: format!("page {}", number),
+                rel: vec![],
+                descriptor: None,
+            })
+            .collect();
+        found.extend(resolve_links(
+            links,
+            &page,
+            site_root,
+            truncate_fragments,
+            regex,
+        ));
+    }
+    Some(found)
+}
+
+/// Fetches every pdf document linked from the pages in `found`, `n_par` at a time, and returns
+/// the links in them. Only urls whose path ends in `.pdf` are fetched, so that extracting links
+/// doesn't request every linked page. Pdf documents linked from other pdf documents are not
+/// followed
+pub async fn follow_pdfs<F, Fut>(
+    found: &[(Url, LinkSource)],
+    fetch: F,
+    n_par: usize,
+    site_root: Option<&Url>,
+    truncate_fragments: bool,
+    regex: &Option<Regex>,
+) -> Vec<(Url, LinkSource)>
+where
+    F: Fn(Url) -> Fut,
+    Fut: Future<Output = Option<Vec<u8>>>,
+{
+    let mut visited: HashSet<&Url> = HashSet::new();
+    let candidates: Vec<Url> = found
+        .iter()
+        .filter(|(url, _)| is_pdf_url(url) && visited.insert(url))
+        .map(|(url, _)| url.clone())
+        .collect();
+    let documents: Vec<(Url, Option<Vec<u8>>)> = stream::iter(candidates)
+        .map(|url| {
+            let document = fetch(url.clone());
+            async move { (url, document.await) }
+        })
+        .buffered(n_par.max(1))
+        .collect()
+        .await;
+    documents
+        .into_iter()
+        .filter_map(|(url, bytes)| {
+            get_links_from_pdf(&bytes?, &url, site_root, truncate_fragments, regex)
+        })
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use futures::executor::block_on;
+    use lopdf::{dictionary, Document as PdfDocument, Object, Stream};
+    use url::Url;
+
+    use crate::{
+        pdf::{follow_pdfs, get_links_from_pdf},
+        req::LinkSource,
+        test_support::found,
+    };
+
+    fn link(uri: &str) -> Object {
+        Object::Dictionary(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Link",
+            "Rect" => vec![0.into(), 0.into(), 10.into(), 10.into()],
+            "A" => dictionary! {
+                "S" => "URI",
+                "URI" => Object::string_literal(uri),
+            },
+        })
+    }
+
+    /// A pdf document with a page for each list of annotations
+    fn pdf_with_annotations(pages: Vec<Vec<Object>>) -> Vec<u8> {
+        let mut pdf = PdfDocument::with_version("1.5");
+        let pages_id = pdf.new_object_id();
+        let content = pdf.add_object(Stream::new(dictionary! {}, vec![]));
+        let kids: Vec<Object> = pages
+            .into_iter()
+            .map(|annotations| {
+                pdf.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content,
+                    "Annots" => annotations,
+                })
+                .into()
+            })
+            .collect();
+        pdf.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog = pdf.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        pdf.trailer.set("Root", catalog);
+        let mut bytes = vec![];
+        pdf.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_get_links_from_pdf() {
+        let note = Object::Dictionary(dictionary! { "Type" => "Annot", "Subtype" => "Text" });
+        let bytes = pdf_with_annotations(vec![
+            vec![link("https://example.com/a"), note],
+            vec![],
+            vec![link("/b.html")],
+        ]);
+
+        let pdf_url = Url::parse("https://example.com/docs/paper.pdf").unwrap();
+        let links: Vec<(String, String, String)> =
+            get_links_from_pdf(&bytes, &pdf_url, None, true, &None)
+                .unwrap()
+                .into_iter()
+                .map(|(url, source)| (url.to_string(), source.page.to_string(), source.text))
+                .collect();
+        assert_eq!(
+            links,
+            vec![
+                (
+                    "https://example.com/a".to_owned(),
+                    "https://example.com/docs/paper.pdf#page=1".to_owned(),
+                    "page 1".to_owned()
+                ),
+                (
+                    "https://example.com/b.html".to_owned(),
+                    "https://example.com/docs/paper.pdf#page=3".to_owned(),
+                    "page 3".to_owned()
+                ),
+            ]
+        );
+        assert!(get_links_from_pdf(b"<html></html>", &pdf_url, None, true, &None).is_none());
+    }
+
+    #[test]
+    fn test_follow_pdfs() {
+        let page = Url::parse("https://example.com/").unwrap();
+        let found: Vec<(Url, LinkSource)> =
+            [("a", "/paper.pdf"), ("a", "/page.html"), ("img", "/c.png")]
+                .iter()
+                .map(|(element, href)| found(&page, href, element, &[]))
+                .collect();
+        let bytes = pdf_with_annotations(vec![vec![link("https://example.com/cited")]]);
+        let fetched = RefCell::new(vec![]);
+        let fetch = |url: Url| {
+            fetched.borrow_mut().push(url.to_string());
+            let pdf = Some(bytes.clone());
+            async move { pdf }
+        };
+        let links: Vec<String> = block_on(follow_pdfs(&found, fetch, 2, None, true, &None))
+            .into_iter()
+            .map(|(url, source)| format!("{} on {}", url, source.page))
+            .collect();
+        assert_eq!(
+            links,
+            vec!["https://example.com/cited on https://example.com/paper.pdf#page=1"]
+        );
+        assert_eq!(fetched.into_inner(), vec!["https://example.com/paper.pdf"]);
+    }
+}
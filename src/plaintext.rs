@@ -0,0 +1,94 @@
+use regex::Regex;
+use url::Url;
+
+use crate::{
+    extract::RawLink,
+    req::{resolve_links, LinkSource},
+};
+
+// Anything that can't be part of a url written in running text ends it
+const URL_PATTERN: &str = r#"(?i)\b(?:https?|ftp)://[^\s<>"'`{}|\\^]+"#;
+// Punctuation that usually ends a sentence rather than the url before it
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', '\'', '"', '*', '_'];
+
+/// Drops the punctuation a url is followed by in text, and closing brackets that have no
+/// opening one within the url, as in `(see https://example.com)`
+fn trim_url(candidate: &str) -> &str {
+    let mut url = candidate;
+    loop {
+        let trimmed = url.trim_end_matches(TRAILING_PUNCTUATION);
+        let trimmed = match trimmed.chars().last() {
+            Some(close @ (')' | ']')) => {
+                let open = if close == ')' { '(' } else { '[' };
+                if trimmed.matches(close).count() > trimmed.matches(open).count() {
+                    &trimmed[..trimmed.len() - 1]
+                } else {
+                    trimmed
+                }
+            }
+            _ => trimmed,
+        };
+        if trimmed == url {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+/// Every url written in a text document, along with the line it is on
+fn text_urls(text: &str) -> Vec<(usize, &str)> {
+    let regex = Regex::new(URL_PATTERN).unwrap();
+    text.lines()
+        .enumerate()
+        .flat_map(|(i, line)| {
+            regex
+                .find_iter(line)
+                .map(move |found| (i + 1, trim_url(found.as_str())))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// The links of a plain text document, such as a readme or a changelog
+pub fn get_links_from_text(
+    text: &str,
+    page: &Url,
+    site_root: Option<&Url>,
+    truncate_fragments: bool,
+    regex: &Option<Regex>,
+) -> Vec<(Url, LinkSource)> {
+    let links = text_urls(text)
+        .into_iter()
+        .map(|(line, href)| RawLink {
+            href,
+            element: "text",
+            attribute: "url",
+            text: format!("line {}", line),
+            rel: vec![],
+            descriptor: None,
+        })
+        .collect();
+    resolve_links(links, page, site_root, truncate_fragments, regex)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plaintext::text_urls;
+
+    #[test]
+    fn test_text_urls() {
+        let text = "Released 1.2 (see https://example.com/changes/1.2).\n\
+                    Docs: <https://docs.rs/rlinks>, wiki at https://en.wikipedia.org/wiki/Rust_(language)\n\
+                    Mirror: FTP://mirror.example.com/pub/ or http://example.com/search?q=a&b=c!";
+        assert_eq!(
+            text_urls(text),
+            vec![
+                (1, "https://example.com/changes/1.2"),
+                (2, "https://docs.rs/rlinks"),
+                (2, "https://en.wikipedia.org/wiki/Rust_(language)"),
+                (3, "FTP://mirror.example.com/pub/"),
+                (3, "http://example.com/search?q=a&b=c"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use curl::easy::Easy;
+use http::{request::Builder, Uri};
+use isahc::{config::Configurable, error::ErrorKind};
+use url::Url;
+
+use crate::{
+    error::RLinksError, outcome::Outcome, req::run_blocking, tls::host_key, url_fix::host_matches,
+};
+
+const TUNNEL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reads the first of the given environment variables that is set. curl ignores the uppercase
+/// HTTP_PROXY, but most tools honour it so we do too
+fn env_var(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+fn parse_proxy(proxy: &str) -> Result<Uri, RLinksError> {
+    // A proxy without a scheme is an http proxy, as in curl
+    let proxy = if proxy.contains("://") {
+        proxy.to_owned()
+    } else {
+        format!("http://{}", proxy)
+    };
+    match proxy.parse::<Uri>() {
+        Ok(uri)
+            if matches!(
+                uri.scheme_str(),
+                Some("http" | "https" | "socks5" | "socks5h")
+            ) =>
+        {
+            Ok(uri)
+        }
+        _ => Err(RLinksError::ProxyParseError(proxy)),
+    }
+}
+
+/// Which proxy each request goes through
+#[derive(Debug, Clone, Default)]
+pub struct ProxyConfig {
+    pub http: Option<Uri>,
+    pub https: Option<Uri>,
+    /// Hosts that are reached directly. Subdomains are included, and `*` matches everything
+    pub no_proxy: Vec<String>,
+    /// Why the tunnel to each host failed, so that we only ask the proxy once
+    pub diagnosed: Arc<Mutex<HashMap<String, Outcome>>>,
+}
+
+impl ProxyConfig {
+    /// A proxy given in the command line is used for everything, otherwise we fall back to the
+    /// usual environment variables
+    pub fn new(proxy: Option<&str>, no_proxy: Vec<&str>) -> Result<Self, RLinksError> {
+        let (http, https) = match proxy {
+            Some(proxy) => {
+                let proxy = parse_proxy(proxy)?;
+                (Some(proxy.clone()), Some(proxy))
+            }
+            None => {
+                let all = env_var(&["all_proxy", "ALL_PROXY"]);
+                let http = env_var(&["http_proxy", "HTTP_PROXY"]).or_else(|| all.clone());
+                let https = env_var(&["https_proxy", "HTTPS_PROXY"]).or(all);
+                (
+                    http.map(|p| parse_proxy(&p)).transpose()?,
+                    https.map(|p| parse_proxy(&p)).transpose()?,
+                )
+            }
+        };
+        let no_proxy: Vec<String> = if no_proxy.is_empty() {
+            env_var(&["no_proxy", "NO_PROXY"])
+                .map(|hosts| hosts.split(',').map(str::to_owned).collect())
+                .unwrap_or_default()
+        } else {
+            no_proxy.into_iter().map(str::to_owned).collect()
+        };
+        Ok(ProxyConfig {
+            http,
+            https,
+            no_proxy: no_proxy
+                .iter()
+                .map(|host| host.trim().trim_start_matches('.').to_owned())
+                .filter(|host| !host.is_empty())
+                .collect(),
+            diagnosed: Default::default(),
+        })
+    }
+    fn bypasses(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default();
+        self.no_proxy
+            .iter()
+            .any(|skipped| host_matches(host, skipped))
+    }
+    /// The proxy a request to `url` should go through, if any
+    pub fn proxy_for(&self, url: &Url) -> Option<&Uri> {
+        if self.bypasses(url) {
+            return None;
+        }
+        match url.scheme() {
+            "http" => self.http.as_ref(),
+            "https" => self.https.as_ref(),
+            _ => None,
+        }
+    }
+    pub fn apply(&self, builder: Builder, url: &Url) -> Builder {
+        if self.http.is_none() && self.https.is_none() {
+            return builder;
+        }
+        builder.proxy(self.proxy_for(url).cloned())
+    }
+}
+
+/// The address to connect to for a proxy, with curl's default ports
+fn proxy_address(proxy: &Uri) -> Option<(String, u16)> {
+    let host = proxy.host()?.trim_start_matches('[').trim_end_matches(']');
+    let port = proxy.port_u16().unwrap_or(match proxy.scheme_str() {
+        Some("https") => 443,
+        _ => 1080,
+    });
+    Some((host.to_owned(), port))
+}
+
+impl ProxyConfig {
+    /// Connects to every proxy once before checking, so that a proxy that is down fails the run
+    /// instead of every link, and any failure later on can be blamed on the link
+    pub fn check(&self, timeout: Duration) -> Result<(), RLinksError> {
+        for proxy in self.http.iter().chain(self.https.iter()) {
+            let reachable = proxy_address(proxy)
+                .and_then(|address| address.to_socket_addrs().ok())
+                .into_iter()
+                .flatten()
+                .any(|address| TcpStream::connect_timeout(&address, timeout).is_ok());
+            if !reachable {
+                return Err(RLinksError::ProxyUnreachable(proxy.to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether a request failed because the name of its proxy could not be resolved, which says
+/// nothing about the link itself
+pub fn is_proxy_error(error: &isahc::Error) -> bool {
+    error
+        .source()
+        .and_then(|source| source.downcast_ref::<curl::Error>())
+        .is_some_and(curl::Error::is_couldnt_resolve_proxy)
+}
+
+/// Errors that happen behind a proxy when the tunnel to the link fails, either because the proxy
+/// refused the CONNECT or because it couldn't reach the host
+pub fn is_tunnel_error(kind: &ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::ConnectionFailed
+            | ErrorKind::Io
+            | ErrorKind::ProtocolViolation
+            | ErrorKind::Unknown
+    )
+}
+
+/// curl reports a CONNECT the proxy refused in the same way as one to a host the proxy could not
+/// reach, so we ask for the tunnel again and look at the proxy's answer. A 403 or 407 is the
+/// fault of the proxy, anything else is the fault of the link. This only happens once per host
+pub async fn classify_tunnel_error(url: &Url, config: &ProxyConfig) -> Outcome {
+    let (key, proxy) = match (host_key(url), config.proxy_for(url)) {
+        (Some(key), Some(proxy)) => (key, proxy.to_string()),
+        _ => return Outcome::ConnectionFailed,
+    };
+    if let Some(outcome) = config.diagnosed.lock().unwrap().get(&key) {
+        return *outcome;
+    }
+    let url = url.clone();
+    let outcome = match run_blocking(move || connect_code(&proxy, &url)).await {
+        Some(Some(403 | 407)) => Outcome::ProxyError,
+        _ => Outcome::ConnectionFailed,
+    };
+    config.diagnosed.lock().unwrap().insert(key, outcome);
+    outcome
+}
+
+/// The status the proxy answered a CONNECT to `url` with. SOCKS proxies don't have one
+fn connect_code(proxy: &str, url: &Url) -> Option<u32> {
+    let mut easy = Easy::new();
+    easy.proxy(proxy).ok()?;
+    // Otherwise curl would also look at the no_proxy environment variable
+    easy.noproxy("").ok()?;
+    easy.url(url.as_str()).ok()?;
+    easy.connect_only(true).ok()?;
+    easy.timeout(TUNNEL_TIMEOUT).ok()?;
+    // Failing is expected, we only want the status of the CONNECT
+    let _ = easy.perform();
+    easy.http_connectcode().ok().filter(|code| *code != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use futures::executor::block_on;
+    use url::Url;
+
+    use http::Uri;
+
+    use crate::{
+        outcome::Outcome,
+        proxy::{classify_tunnel_error, proxy_address, ProxyConfig},
+    };
+
+    /// A proxy that answers every CONNECT with `status`
+    fn fake_proxy(status: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read(&mut [0; 1024]);
+                let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[test]
+    fn test_proxy_for() {
+        let config = ProxyConfig::new(
+            Some("socks5://proxy.corp:1080"),
+            vec!["intranet.corp", ".local"],
+        )
+        .unwrap();
+        let proxy_for = |url: &str| config.proxy_for(&Url::parse(url).unwrap()).cloned();
+        assert_eq!(
+            proxy_for("https://example.com/").unwrap().to_string(),
+            "socks5://proxy.corp:1080/"
+        );
+        assert!(proxy_for("http://docs.intranet.corp/").is_none());
+        assert!(proxy_for("http://printer.local/").is_none());
+        assert!(proxy_for("file:///tmp/a.html").is_none());
+        assert!(ProxyConfig::new(Some("ftp://proxy.corp"), vec![]).is_err());
+        assert_eq!(
+            ProxyConfig::new(Some("proxy.corp:3128"), vec![])
+                .unwrap()
+                .http
+                .unwrap()
+                .to_string(),
+            "http://proxy.corp:3128/"
+        );
+    }
+
+    #[test]
+    fn test_proxy_address() {
+        let address = |proxy: &str| proxy_address(&proxy.parse::<Uri>().unwrap()).unwrap();
+        assert_eq!(
+            address("http://proxy.corp"),
+            ("proxy.corp".to_owned(), 1080)
+        );
+        assert_eq!(
+            address("https://proxy.corp"),
+            ("proxy.corp".to_owned(), 443)
+        );
+        assert_eq!(address("socks5://[::1]:9050"), ("::1".to_owned(), 9050));
+    }
+
+    #[test]
+    fn test_classify_tunnel_error() {
+        let classify = |status, url: &str| {
+            let config = ProxyConfig::new(Some(&fake_proxy(status)), vec![]).unwrap();
+            block_on(classify_tunnel_error(&Url::parse(url).unwrap(), &config))
+        };
+        assert_eq!(
+            classify("407 Proxy Authentication Required", "https://example.com/"),
+            Outcome::ProxyError
+        );
+        assert_eq!(
+            classify("403 Forbidden", "https://example.com/"),
+            Outcome::ProxyError
+        );
+        assert_eq!(
+            classify("502 Bad Gateway", "https://example.com/"),
+            Outcome::ConnectionFailed
+        );
+    }
+}
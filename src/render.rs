@@ -0,0 +1,57 @@
+use std::process::Command;
+
+use url::Url;
+
+use crate::error::RLinksError;
+
+// Where the url goes in the render command. Without it, the url is the last argument
+const URL_PLACEHOLDER: &str = "{url}";
+
+/// Runs `command` through the shell to render `url`, and returns the html it prints. This is
+/// how we see the links of pages that are built by scripts, e.g. with
+/// `chromium --headless --dump-dom {url}`. The url is passed as a positional parameter, so
+/// nothing in it is interpreted by the shell
+pub fn render_page(command: &str, url: &Url) -> Result<String, RLinksError> {
+    let script = if command.contains(URL_PLACEHOLDER) {
+        command.replace(URL_PLACEHOLDER, "\"$1\"")
+    } else {
+        format!("{} \"$1\"", command)
+    };
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(&script)
+        .arg("rlinks")
+        .arg(url.as_str())
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+        let reason = if stderr.is_empty() {
+            output.status.to_string()
+        } else {
+            stderr
+        };
+        return Err(RLinksError::RenderError(url.to_owned(), reason));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::render::render_page;
+
+    #[test]
+    fn test_render_page() {
+        let url = Url::parse("https://example.com/app?a=1&b='2'").unwrap();
+        assert_eq!(
+            render_page("printf '<a href=\"%s\">'", &url).unwrap(),
+            "<a href=\"https://example.com/app?a=1&b=%272%27\">"
+        );
+        assert_eq!(
+            render_page("echo {url} rendered", &url).unwrap().trim(),
+            "https://example.com/app?a=1&b=%272%27 rendered"
+        );
+        assert!(render_page("echo oops >&2; false", &url).is_err());
+    }
+}
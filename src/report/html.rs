@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    outcome::{CheckResult, Outcome},
+    report::Report,
+};
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;width:100%;margin-bottom:2em}\
+th,td{border:1px solid #ccc;padding:4px 8px;text-align:left;vertical-align:top}\
+th{background:#eee;cursor:pointer;user-select:none}\
+tr.broken td{background:#fdecea}\
+.summary td:first-child{font-weight:bold}\
+#filter{margin-bottom:1em;padding:4px;width:30em}";
+
+// Sorts the links table when a header is clicked and filters it as the user types
+const SCRIPT: &str = "const table=document.getElementById('links');\
+const rows=()=>Array.from(table.tBodies[0].rows);\
+table.querySelectorAll('th').forEach((th,i)=>{th.addEventListener('click',()=>{\
+const asc=th.dataset.asc!=='true';th.dataset.asc=asc;\
+const key=r=>{const c=r.cells[i];return c.dataset.sort!==undefined?parseFloat(c.dataset.sort):c.textContent.toLowerCase();};\
+rows().sort((a,b)=>{const x=key(a),y=key(b);return (x<y?-1:x>y?1:0)*(asc?1:-1);})\
+.forEach(r=>table.tBodies[0].appendChild(r));});});\
+const filter=()=>{const q=document.getElementById('filter').value.toLowerCase();\
+const c=document.getElementById('category').value;\
+rows().forEach(r=>{const show=r.textContent.toLowerCase().includes(q)&&(c===''||r.dataset.category===c);\
+r.style.display=show?'':'none';});};\
+document.getElementById('filter').addEventListener('input',filter);\
+document.getElementById('category').addEventListener('change',filter);";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn link(url: &str) -> String {
+    format!("<a href=\"{0}\">{0}</a>", escape(url))
+}
+
+fn summary(report: &Report) -> String {
+    let mut counts: BTreeMap<Outcome, usize> = BTreeMap::new();
+    report.results.iter().for_each(|result| {
+        *counts.entry(result.outcome).or_default() += 1;
+    });
+    let broken = report
+        .results
+        .iter()
+        .filter(|r| r.outcome.is_broken())
+        .count();
+    let mut rows = format!(
+        "<tr><td>Start page</td><td>{}</td></tr>\
+         <tr><td>Links checked</td><td>{}</td></tr>\
+         <tr><td>Domains</td><td>{}</td></tr>\
+         <tr><td>Broken</td><td>{}</td></tr>",
+        link(report.start_url.as_str()),
+        report.results.len(),
+        report.links.hash_map.len(),
+        broken
+    );
+    if let Some(unchecked) = report.links.unchecked_summary() {
+        rows.push_str(&format!(
+            "<tr><td>Not checked</td><td>{}</td></tr>",
+            escape(&unchecked)
+        ));
+    }
+    counts.iter().for_each(|(outcome, count)| {
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td></tr>",
+            escape(outcome.label()),
+            count
+        ))
+    });
+    format!("<h2>Summary</h2><table class=\"summary\">{}</table>", rows)
+}
+
+fn links_table(report: &Report) -> String {
+    let mut categories: Vec<Outcome> = report.results.iter().map(|r| r.outcome).collect();
+    categories.sort();
+    categories.dedup();
+    let options: String = categories
+        .iter()
+        .map(|c| format!("<option>{}</option>", escape(c.label())))
+        .collect();
+    let rows: String = report
+        .sorted_results()
+        .iter()
+        .map(|result| {
+            let referrers: Vec<String> = report
+                .referrers(&result.url)
+                .iter()
+                .map(|page| link(page.as_str()))
+                .collect();
+            format!(
+                "<tr class=\"{}\" data-category=\"{}\"><td>{}</td><td data-sort=\"{}\">{}</td>\
+                 <td>{}</td><td data-sort=\"{}\">{}</td><td>{}</td><td>{}</td></tr>",
+                if result.outcome.is_broken() {
+                    "broken"
+                } else {
+                    "ok"
+                },
+                escape(result.outcome.label()),
+                link(result.url.as_str()),
+                result.status.as_u16(),
+                result.status,
+                escape(result.outcome.label()),
+                result.elapsed.as_millis(),
+                result.elapsed.as_millis(),
+                result
+                    .redirect
+                    .as_ref()
+                    .map_or_else(String::new, |url| link(url.as_str())),
+                referrers.join("<br>")
+            )
+        })
+        .collect();
+    format!(
+        "<h2>All links</h2>\
+         <input id=\"filter\" placeholder=\"Filter links\">\
+         <select id=\"category\"><option value=\"\">All categories</option>{}</select>\
+         <table id=\"links\"><thead><tr><th>URL</th><th>Status</th><th>Category</th>\
+         <th>Time (ms)</th><th>Redirected to</th><th>Referring pages</th></tr></thead><tbody>{}</tbody></table>",
+        options, rows
+    )
+}
+
+fn grouped<K: AsRef<str>>(title: &str, groups: &BTreeMap<K, Vec<&CheckResult>>) -> String {
+    if groups.is_empty() {
+        return format!("<h2>{}</h2><p>No broken links</p>", escape(title));
+    }
+    let sections: String = groups
+        .iter()
+        .map(|(key, results)| {
+            let items: String = results
+                .iter()
+                .map(|r| {
+                    format!(
+                        "<li>{} ({}, {})</li>",
+                        link(r.url.as_str()),
+                        r.status,
+                        escape(r.outcome.label())
+                    )
+                })
+                .collect();
+            format!("<h3>{}</h3><ul>{}</ul>", escape(key.as_ref()), items)
+        })
+        .collect();
+    format!("<h2>{}</h2>{}", escape(title), sections)
+}
+
+fn certificates(report: &Report) -> String {
+    if report.certificates.is_empty() {
+        return String::new();
+    }
+    let rows: String = report
+        .certificates
+        .values()
+        .map(|expiry| {
+            format!(
+                "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td></tr>",
+                if expiry.expiring { "broken" } else { "ok" },
+                escape(&expiry.host),
+                escape(&expiry.expires),
+                expiry.days_left
+            )
+        })
+        .collect();
+    format!(
+        "<h2>Certificates</h2><table><thead><tr><th>Host</th><th>Expires</th>\
+         <th>Days left</th></tr></thead><tbody>{}</tbody></table>",
+        rows
+    )
+}
+
+/// Renders a single self-contained html page, with no external assets
+pub fn render(report: &Report) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <title>rlinks report for {title}</title><style>{style}</style></head><body>\
+         <h1>rlinks report for {title}</h1>{summary}{by_page}{by_domain}{certificates}{table}\
+         <script>{script}</script></body></html>",
+        title = escape(report.start_url.as_str()),
+        style = STYLE,
+        summary = summary(report),
+        by_page = grouped("Broken links by page", &report.broken_by_page()),
+        by_domain = grouped("Broken links by domain", &report.broken_by_domain()),
+        certificates = certificates(report),
+        table = links_table(report),
+        script = SCRIPT
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, time::Duration};
+
+    use http::StatusCode;
+    use url::Url;
+
+    use crate::{
+        outcome::{CheckResult, Outcome},
+        report::{html::render, Report},
+        req::Links,
+        test_support::{found, result},
+    };
+
+    #[test]
+    fn test_render() {
+        let page = Url::parse("https://example.com/").unwrap();
+        let links = Links::from_found(vec![
+            found(&page, "https://other.org/gone", "a", &[]),
+            found(&page, "/about", "a", &[]),
+        ]);
+        let (gone, about) = (
+            Url::parse("https://other.org/gone").unwrap(),
+            Url::parse("https://example.com/about").unwrap(),
+        );
+        let result = |url: &Url, status, outcome| CheckResult {
+            elapsed: Duration::from_millis(120),
+            ..result(url.as_str(), status, outcome)
+        };
+        let results = vec![
+            result(&gone, StatusCode::NOT_FOUND, Outcome::HttpError),
+            result(&about, StatusCode::OK, Outcome::Ok),
+        ];
+        let certificates = BTreeMap::new();
+        let html = render(&Report {
+            start_url: &page,
+            links: &links,
+            results: &results,
+            certificates: &certificates,
+        });
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>rlinks report for https://example.com/</title>"));
+        assert!(html.contains("<tr><td>Links checked</td><td>2</td></tr>"));
+        assert!(html.contains("<tr><td>Broken</td><td>1</td></tr>"));
+        // Broken links are grouped by the page they are on and by the domain they point to
+        assert!(html.contains(
+            "<h3>https://example.com/</h3><ul><li>\
+             <a href=\"https://other.org/gone\">https://other.org/gone</a> \
+             (404 Not Found, http error)</li></ul>"
+        ));
+        assert!(html.contains("<h3>other.org</h3>"));
+        // The table is sorted by url, and every row says where it was found
+        let table = &html[html.find("<table id=\"links\">").unwrap()..];
+        assert!(table.find(about.as_str()).unwrap() < table.find(gone.as_str()).unwrap());
+        assert!(table.contains(
+            "<tr class=\"broken\" data-category=\"http error\">\
+             <td><a href=\"https://other.org/gone\">https://other.org/gone</a></td>\
+             <td data-sort=\"404\">404 Not Found</td><td>http error</td>\
+             <td data-sort=\"120\">120</td><td></td>\
+             <td><a href=\"https://example.com/\">https://example.com/</a></td></tr>"
+        ));
+    }
+}
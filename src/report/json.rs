@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use url::Url;
+
+use crate::{
+    error::RLinksError,
+    outcome::Outcome,
+    report::Report,
+    req::{LinkSource, Links},
+    tls::CertificateExpiry,
+};
+
+#[derive(Serialize)]
+struct SourceRecord<'a> {
+    #[serde(flatten)]
+    source: &'a LinkSource,
+    internal: bool,
+}
+
+#[derive(Serialize)]
+struct LinkRecord<'a> {
+    url: &'a Url,
+    sources: Vec<SourceRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct HostRecord<'a> {
+    host: String,
+    links: Vec<LinkRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct CheckRecord<'a> {
+    url: &'a Url,
+    host: &'a str,
+    status: u16,
+    outcome: Outcome,
+    broken: bool,
+    redirect: Option<&'a Url>,
+    response_time_ms: u128,
+    https_upgrade: Option<&'a Url>,
+    sources: Vec<SourceRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct CheckReport<'a> {
+    start_url: &'a Url,
+    links: Vec<CheckRecord<'a>>,
+    certificates: Vec<&'a CertificateExpiry>,
+    /// Links with schemes we can't check, counted by scheme
+    unchecked_schemes: &'a BTreeMap<String, usize>,
+}
+
+fn source_records<'a>(links: &'a Links, url: &Url) -> Vec<SourceRecord<'a>> {
+    links
+        .sources
+        .get(url)
+        .into_iter()
+        .flatten()
+        .map(|source| SourceRecord {
+            source,
+            internal: source.is_internal(url),
+        })
+        .collect()
+}
+
+/// Check results sorted by url. This is also the format `--baseline` reads
+pub fn render(report: &Report) -> Result<String, RLinksError> {
+    let links = report
+        .sorted_results()
+        .into_iter()
+        .map(|result| CheckRecord {
+            url: &result.url,
+            host: result.url.host_str().unwrap_or_default(),
+            status: result.status.as_u16(),
+            outcome: result.outcome,
+            broken: result.outcome.is_broken(),
+            redirect: result.redirect.as_ref(),
+            response_time_ms: result.elapsed.as_millis(),
+            https_upgrade: result.https_upgrade.as_ref(),
+            sources: source_records(report.links, &result.url),
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&CheckReport {
+        start_url: report.start_url,
+        links,
+        certificates: report.certificates.values().collect(),
+        unchecked_schemes: &report.links.unchecked_schemes,
+    })?)
+}
+
+/// Dumped links grouped by host, with hosts and urls sorted alphabetically
+pub fn render_links(links: &Links) -> Result<String, RLinksError> {
+    let mut hosts: Vec<HostRecord> = links
+        .hash_map
+        .iter()
+        .map(|(host, urls)| {
+            let mut urls: Vec<&Url> = urls.iter().collect();
+            urls.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+            let links = urls
+                .into_iter()
+                .map(|url| LinkRecord {
+                    url,
+                    sources: source_records(links, url),
+                })
+                .collect();
+            HostRecord {
+                host: host.to_string(),
+                links,
+            }
+        })
+        .collect();
+    hosts.sort_by(|a, b| a.host.cmp(&b.host));
+    Ok(serde_json::to_string_pretty(&hosts)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, time::Duration};
+
+    use http::StatusCode;
+    use serde_json::{json, Value};
+    use url::Url;
+
+    use crate::{
+        outcome::{CheckResult, Outcome},
+        report::{
+            json::{render, render_links},
+            Report,
+        },
+        req::{LinkSource, Links},
+        test_support::{found, result},
+    };
+
+    #[test]
+    fn test_render_links() {
+        let page = Url::parse("https://example.com/").unwrap();
+        let found = |href| {
+            let (url, source) = found(&page, href, "a", &["sponsored"]);
+            let text = "text".to_owned();
+            (url, LinkSource { text, ..source })
+        };
+        let links = Links::from_found(vec![found("/b"), found("https://other.org/"), found("/a")]);
+        let rendered: Value = serde_json::from_str(&render_links(&links).unwrap()).unwrap();
+        let source = |href: &str, internal: bool| {
+            json!({
+                "page": "https://example.com/",
+                "href": href,
+                "element": "a",
+                "attribute": "href",
+                "text": "text",
+                "rel": ["sponsored"],
+                "descriptor": null,
+                "internal": internal,
+            })
+        };
+        assert_eq!(
+            rendered,
+            json!([
+                {
+                    "host": "example.com",
+                    "links": [
+                        {"url": "https://example.com/a", "sources": [source("/a", true)]},
+                        {"url": "https://example.com/b", "sources": [source("/b", true)]},
+                    ],
+                },
+                {
+                    "host": "other.org",
+                    "links": [
+                        {
+                            "url": "https://other.org/",
+                            "sources": [source("https://other.org/", false)],
+                        },
+                    ],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let page = Url::parse("https://example.com/").unwrap();
+        let links = Links::from_found(vec![found(&page, "http://old.org/", "a", &[])]);
+        let results = vec![CheckResult {
+            elapsed: Duration::from_millis(5),
+            https_upgrade: Some(Url::parse("https://old.org/").unwrap()),
+            ..result("http://old.org/", StatusCode::OK, Outcome::Ok)
+        }];
+        let certificates = BTreeMap::new();
+        let rendered: Value = serde_json::from_str(
+            &render(&Report {
+                start_url: &page,
+                links: &links,
+                results: &results,
+                certificates: &certificates,
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        let mut link = rendered["links"][0].clone();
+        link.as_object_mut().unwrap().remove("sources");
+        assert_eq!(
+            link,
+            json!({
+                "url": "http://old.org/",
+                "host": "old.org",
+                "status": 200,
+                "outcome": "ok",
+                "broken": false,
+                "redirect": null,
+                "response_time_ms": 5,
+                "https_upgrade": "https://old.org/",
+            })
+        );
+        assert_eq!(rendered["links"][0]["sources"][0]["internal"], false);
+    }
+}
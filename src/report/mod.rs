@@ -0,0 +1,86 @@
+use std::{collections::BTreeMap, fs::File, io::Write, path::Path};
+
+use url::Url;
+
+use crate::{
+    error::RLinksError,
+    outcome::CheckResult,
+    req::Links,
+    text::print_diagnostic,
+    tls::{host_key, CertificateExpiry},
+};
+
+pub mod html;
+pub mod json;
+pub mod table;
+
+/// Everything a report needs: the results of checking and where each link came from
+pub struct Report<'a> {
+    pub start_url: &'a Url,
+    pub links: &'a Links,
+    pub results: &'a [CheckResult],
+    /// Certificate expiry of every https host, keyed by host
+    pub certificates: &'a BTreeMap<String, CertificateExpiry>,
+}
+
+impl<'a> Report<'a> {
+    /// Results sorted by url so that reports are stable between runs
+    pub fn sorted_results(&self) -> Vec<&'a CheckResult> {
+        let mut results: Vec<&CheckResult> = self.results.iter().collect();
+        results.sort_by(|a, b| a.url.as_str().cmp(b.url.as_str()));
+        results
+    }
+    pub fn referrers(&self, url: &Url) -> Vec<&'a Url> {
+        self.links
+            .sources
+            .get(url)
+            .map(|sources| sources.iter().map(|source| &source.page).collect())
+            .unwrap_or_default()
+    }
+    pub fn certificate(&self, url: &Url) -> Option<&'a CertificateExpiry> {
+        self.certificates.get(&host_key(url)?)
+    }
+    /// Broken results keyed by the page they were found in
+    pub fn broken_by_page(&self) -> BTreeMap<&'a str, Vec<&'a CheckResult>> {
+        let mut map: BTreeMap<&str, Vec<&CheckResult>> = BTreeMap::new();
+        for result in self.sorted_results() {
+            if !result.outcome.is_broken() {
+                continue;
+            }
+            for page in self.referrers(&result.url) {
+                map.entry(page.as_str()).or_default().push(result);
+            }
+        }
+        map
+    }
+    /// Broken results keyed by the host they point to
+    pub fn broken_by_domain(&self) -> BTreeMap<String, Vec<&'a CheckResult>> {
+        let mut map: BTreeMap<String, Vec<&CheckResult>> = BTreeMap::new();
+        for result in self.sorted_results() {
+            if result.outcome.is_broken() {
+                let host = result.url.host_str().unwrap_or_default().to_owned();
+                map.entry(host).or_default().push(result);
+            }
+        }
+        map
+    }
+}
+
+/// Writes a report to a file, or to stdout when there is no file
+pub fn write_report(contents: &str, output_file: Option<&str>) -> Result<(), RLinksError> {
+    match output_file {
+        Some(output_file) => write_to_file(contents, output_file),
+        None => {
+            println!("{}", contents);
+            Ok(())
+        }
+    }
+}
+
+pub fn write_to_file(string: &str, output_file: &str) -> Result<(), RLinksError> {
+    let path = Path::new(output_file);
+    let mut file = File::create(path)?;
+    file.write_all(string.as_bytes())?;
+    print_diagnostic(&format!("successfully wrote to {}", path.display()));
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use csv::WriterBuilder;
+
+use crate::{error::RLinksError, report::Report, req::Links};
+
+const CHECK_HEADER: [&str; 12] = [
+    "source_page",
+    "url",
+    "host",
+    "element",
+    "anchor_text",
+    "descriptor",
+    "status_code",
+    "outcome",
+    "redirect",
+    "response_time_ms",
+    "certificate_expires",
+    "https_upgrade",
+];
+const DUMP_HEADER: [&str; 8] = [
+    "source_page",
+    "url",
+    "host",
+    "element",
+    "anchor_text",
+    "descriptor",
+    "rel",
+    "internal",
+];
+
+fn into_string(writer: csv::Writer<Vec<u8>>) -> Result<String, RLinksError> {
+    let bytes = writer
+        .into_inner()
+        .map_err(|err| RLinksError::IoError(err.into_error()))?;
+    // The writer was only given strings, so the output is valid utf-8
+    Ok(String::from_utf8(bytes).unwrap())
+}
+
+/// One row per (source page, link) pair, separated by `delimiter`
+pub fn render(report: &Report, delimiter: u8) -> Result<String, RLinksError> {
+    let mut writer = WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(vec![]);
+    writer.write_record(CHECK_HEADER)?;
+    for result in report.sorted_results() {
+        let status = result.status.as_u16().to_string();
+        let redirect = result.redirect.as_ref().map_or("", |url| url.as_str());
+        let elapsed = result.elapsed.as_millis().to_string();
+        let expires = report
+            .certificate(&result.url)
+            .map_or("", |expiry| expiry.expires.as_str());
+        let upgrade = result.https_upgrade.as_ref().map_or("", |url| url.as_str());
+        for source in report.links.sources.get(&result.url).into_iter().flatten() {
+            writer.write_record([
+                source.page.as_str(),
+                result.url.as_str(),
+                result.url.host_str().unwrap_or_default(),
+                &format!("{}[{}]", source.element, source.attribute),
+                &source.text,
+                source.descriptor.as_deref().unwrap_or_default(),
+                &status,
+                result.outcome.label(),
+                redirect,
+                &elapsed,
+                expires,
+                upgrade,
+            ])?;
+        }
+    }
+    into_string(writer)
+}
+
+/// Same layout as `render`, without the columns that need the links to be checked
+pub fn render_links(links: &Links, delimiter: u8) -> Result<String, RLinksError> {
+    let mut writer = WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(vec![]);
+    writer.write_record(DUMP_HEADER)?;
+    let mut urls: Vec<_> = links.sources.keys().collect();
+    urls.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    for url in urls {
+        for source in &links.sources[url] {
+            writer.write_record([
+                source.page.as_str(),
+                url.as_str(),
+                url.host_str().unwrap_or_default(),
+                &format!("{}[{}]", source.element, source.attribute),
+                &source.text,
+                source.descriptor.as_deref().unwrap_or_default(),
+                &source.rel.join(" "),
+                if source.is_internal(url) {
+                    "internal"
+                } else {
+                    "external"
+                },
+            ])?;
+        }
+    }
+    into_string(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, time::Duration};
+
+    use http::StatusCode;
+    use url::Url;
+
+    use crate::{
+        outcome::{CheckResult, Outcome},
+        report::{
+            table::{render, render_links},
+            Report,
+        },
+        req::Links,
+        test_support::{found, result},
+    };
+
+    #[test]
+    fn test_render_links() {
+        let page = Url::parse("https://example.com/").unwrap();
+        let links = Links::from_found(vec![
+            found(&page, "https://b.org/x", "a", &["nofollow", "ugc"]),
+            found(&page, "/z.png", "img", &[]),
+            found(&page, "https://a.org/", "a", &[]),
+        ]);
+        assert_eq!(
+            render_links(&links, b',').unwrap(),
+            "source_page,url,host,element,anchor_text,descriptor,rel,internal\n\
+             https://example.com/,https://a.org/,a.org,a[href],,,,external\n\
+             https://example.com/,https://b.org/x,b.org,a[href],,,nofollow ugc,external\n\
+             https://example.com/,https://example.com/z.png,example.com,img[src],,,,internal\n"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let page = Url::parse("https://example.com/").unwrap();
+        let links = Links::from_found(vec![
+            found(&page, "http://old.org/a", "a", &[]),
+            found(&page, "/b", "a", &[]),
+        ]);
+        let result = |url, https_upgrade: Option<&str>| CheckResult {
+            elapsed: Duration::from_millis(5),
+            https_upgrade: https_upgrade.map(|url| Url::parse(url).unwrap()),
+            ..result(url, StatusCode::OK, Outcome::Ok)
+        };
+        let results = vec![
+            result("https://example.com/b", None),
+            result("http://old.org/a", Some("https://old.org/a")),
+        ];
+        let certificates = BTreeMap::new();
+        let report = Report {
+            start_url: &page,
+            links: &links,
+            results: &results,
+            certificates: &certificates,
+        };
+        assert_eq!(
+            render(&report, b'\t').unwrap(),
+            "source_page\turl\thost\telement\tanchor_text\tdescriptor\tstatus_code\toutcome\t\
+             redirect\tresponse_time_ms\tcertificate_expires\thttps_upgrade\n\
+             https://example.com/\thttp://old.org/a\told.org\ta[href]\t\t\t200\tok\t\t5\t\t\
+             https://old.org/a\n\
+             https://example.com/\thttps://example.com/b\texample.com\ta[href]\t\t\t200\tok\t\t5\t\t\n"
+        );
+    }
+}
@@ -1,75 +1,146 @@
 use std::{
-    collections::{HashMap, HashSet},
-    iter::FromIterator,
-    time::Duration,
+    collections::{BTreeMap, HashMap, HashSet},
+    thread,
+    time::{Duration, Instant},
 };
 
-use crate::{error::RLinksError, text::ColorsExt, url_fix::fix_malformed_url};
-use futures::{stream, StreamExt};
-use http::{header::USER_AGENT, StatusCode};
+use crate::{
+    cli::{ClientConfig, RequestConfig},
+    css::follow_stylesheets,
+    data_url::check_data_url,
+    error::RLinksError,
+    extract::{get_raw_links, RawLink},
+    feed::{discover_feeds, get_links_from_feed},
+    ftp::check_ftp_url,
+    json_links::{get_links_from_json, JsonSelector},
+    lint::{lint_href, print_href_issues, HrefIssue},
+    local::check_file_url,
+    mailto::check_mailto_url,
+    outcome::{CheckResult, Outcome},
+    pdf::{follow_pdfs, get_links_from_pdf},
+    plaintext::get_links_from_text,
+    proxy::{classify_tunnel_error, is_proxy_error, is_tunnel_error},
+    render::render_page,
+    resource::{check_resource, ResourceKind},
+    status::StatusCodeKind,
+    text::{print_diagnostic, ColorsExt},
+    tls::{classify_tls_error, is_tls_error},
+    url_fix::{fix_local_url, fix_malformed_url},
+};
+use futures::{channel::oneshot, stream, StreamExt};
+use http::{
+    header::{CONTENT_TYPE, LOCATION, USER_AGENT},
+    StatusCode,
+};
 use indicatif::{ProgressBar, ProgressStyle};
 use isahc::{
     config::{Configurable, RedirectPolicy, VersionNegotiation},
+    cookies::CookieJar,
     error::ErrorKind,
     prelude::*,
     AsyncBody, HttpClient, Request, Response,
 };
 
 use regex::Regex;
-use select::{document::Document, predicate::Name};
+use select::document::Document;
+use serde::Serialize;
 use url::{Host, Url};
 
-#[derive(Debug)]
-enum StatusCodeKind {
-    Valid(StatusCode),
-    MethodNotAllowed(StatusCode),
-    Fail(StatusCode),
-}
-fn get_status_code_kind(x: StatusCode) -> StatusCodeKind {
-    match x {
-        x if x.is_success() | x.is_redirection() => StatusCodeKind::Valid(x),
-        x if x == StatusCode::METHOD_NOT_ALLOWED => StatusCodeKind::MethodNotAllowed(x),
-        x => StatusCodeKind::Fail(x),
-    }
-}
-#[derive(Debug)]
-enum RequestType {
+// Non-standard status used by nginx, our fake responses need some status code
+const SSL_CERTIFICATE_ERROR: u16 = 495;
+// Schemes of the links we know how to check, any other link is only counted
+const CHECKED_SCHEMES: [&str; 6] = ["http", "https", "file", "ftp", "data", "mailto"];
+// Redirects we follow before giving up, both in curl and in the ones we follow ourselves
+const MAX_REDIRECTS: u32 = 5;
+#[derive(Debug, Clone, Copy)]
+pub enum RequestType {
     Get,
     Head,
 }
-pub fn get_client(timeout: Duration) -> HttpClient {
+pub fn get_client(config: &ClientConfig, cookie_jar: &CookieJar) -> HttpClient {
     debug!("Getting client");
+    let timeout = Duration::from_secs(config.timeout);
     HttpClient::builder()
         .version_negotiation(VersionNegotiation::http11())
         .timeout(timeout)
         .connect_timeout(timeout)
-        .redirect_policy(RedirectPolicy::Limit(5))
-        .cookies()
+        .redirect_policy(RedirectPolicy::Limit(MAX_REDIRECTS))
+        .cookie_jar(cookie_jar.clone())
         .build()
         .unwrap()
 }
-// .danger_allow_unsafe_ssl(true)
 // This generates a response with a timeout status so that we can make errors into response
-fn build_fake_response(status: StatusCode) -> Response<AsyncBody> {
+fn build_fake_response(status: StatusCode, outcome: Outcome) -> Response<AsyncBody> {
     Response::builder()
         .status(status)
+        .extension(outcome)
         .body(AsyncBody::empty())
         .unwrap()
 }
-async fn request_with_header(
+/// Requests a url with our headers. Requests that carry credentials don't follow redirects in
+/// curl, so we follow those here, keeping the credentials only while we stay on the hosts they
+/// are meant for
+pub async fn request_with_header(
+    client: &HttpClient,
+    request: &RequestConfig,
+    request_type: RequestType,
+    url: &Url,
+) -> Result<Response<AsyncBody>, RLinksError> {
+    let mut response = send_request(client, request, request_type, url).await?;
+    let mut current = url.to_owned();
+    for _ in 0..MAX_REDIRECTS {
+        if request.headers.follows_redirects(&current) {
+            return Ok(response);
+        }
+        let next = match redirect_target(&current, &response) {
+            Some(next) => next,
+            None => return Ok(response),
+        };
+        response = send_request(client, request, request_type, &next).await?;
+        current = next;
+    }
+    match redirect_target(&current, &response) {
+        Some(_) if !request.headers.follows_redirects(&current) => {
+            info!("[ERROR] Too many redirects for {}", url);
+            Ok(build_fake_response(
+                StatusCode::MISDIRECTED_REQUEST,
+                Outcome::TooManyRedirects,
+            ))
+        }
+        _ => Ok(response),
+    }
+}
+
+/// Where a redirect response sends us, if it is one
+fn redirect_target(url: &Url, response: &Response<AsyncBody>) -> Option<Url> {
+    if !response.status().is_redirection() {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    url.join(location).ok()
+}
+
+async fn send_request(
     client: &HttpClient,
-    user_agent: &str,
+    request: &RequestConfig,
     request_type: RequestType,
     url: &Url,
 ) -> Result<Response<AsyncBody>, RLinksError> {
-    let req = match request_type {
+    let builder = match request_type {
         RequestType::Head => Request::head(url.clone().as_str()),
         RequestType::Get => Request::get(url.clone().as_str()),
     }
-    .header(USER_AGENT, user_agent)
-    .body(AsyncBody::empty())
-    // This unwrap is safe, we are merely building the request
-    .unwrap();
+    .header(USER_AGENT, request.user_agent.as_str());
+    let builder = request.headers.apply(builder, url);
+    let builder = request
+        .tls
+        .apply(builder, url, request.headers.applies_to(url));
+    let req = request
+        .proxy
+        .apply(builder, url)
+        .body(AsyncBody::empty())
+        // This unwrap is safe, we are merely building the request
+        .unwrap();
     debug!("Requesting {}", url);
     match client
         .send_async(req)
@@ -78,212 +149,559 @@ async fn request_with_header(
     {
         Ok(e) => Ok(e),
 
+        Err(e) if is_tls_error(&e) => {
+            info!("[ERROR] {} for {}", e, url);
+            // Behind a proxy we may not be able to connect directly to diagnose the failure
+            let outcome = match request.proxy.proxy_for(url) {
+                Some(_) => Outcome::TlsError,
+                None => classify_tls_error(&e, url, &request.tls).await,
+            };
+            Ok(build_fake_response(
+                StatusCode::from_u16(SSL_CERTIFICATE_ERROR).unwrap(),
+                outcome,
+            ))
+        }
+        Err(e) if request.proxy.proxy_for(url).is_some() && is_proxy_error(&e) => {
+            info!("[ERROR] Proxy failed for {}: {}", url, e);
+            Ok(build_fake_response(
+                StatusCode::BAD_GATEWAY,
+                Outcome::ProxyError,
+            ))
+        }
+        Err(e) if request.proxy.proxy_for(url).is_some() && is_tunnel_error(e.kind()) => {
+            info!("[ERROR] No tunnel through the proxy to {}: {}", url, e);
+            Ok(match classify_tunnel_error(url, &request.proxy).await {
+                Outcome::ProxyError => {
+                    build_fake_response(StatusCode::BAD_GATEWAY, Outcome::ProxyError)
+                }
+                outcome => build_fake_response(StatusCode::NOT_FOUND, outcome),
+            })
+        }
+
         // Timeouts become errors, but we want to make these not error just yet, so we make them into fake responses
         Err(e) if e.kind() == ErrorKind::Timeout => {
             info!("[ERROR] Timeout for {}", url);
-            Ok(build_fake_response(StatusCode::REQUEST_TIMEOUT))
+            Ok(build_fake_response(
+                StatusCode::REQUEST_TIMEOUT,
+                Outcome::Timeout,
+            ))
         }
         Err(e) if e.kind() == ErrorKind::NameResolution => {
             info!("[ERROR] Could not resolve host for {}", url);
-            Ok(build_fake_response(StatusCode::NOT_FOUND))
+            Ok(build_fake_response(
+                StatusCode::NOT_FOUND,
+                Outcome::Unresolved,
+            ))
         }
         Err(e) if e.kind() == ErrorKind::ConnectionFailed => {
             info!("[ERROR] Connection failed for {}", url);
-            Ok(build_fake_response(StatusCode::NOT_FOUND))
+            Ok(build_fake_response(
+                StatusCode::NOT_FOUND,
+                Outcome::ConnectionFailed,
+            ))
         }
         Err(e) if e.kind() == ErrorKind::TooManyRedirects => {
             info!("[ERROR] Too many redirects for {}", url);
-            Ok(build_fake_response(StatusCode::MISDIRECTED_REQUEST))
+            Ok(build_fake_response(
+                StatusCode::MISDIRECTED_REQUEST,
+                Outcome::TooManyRedirects,
+            ))
         }
         Err(e) if e.kind() == ErrorKind::RequestBodyNotRewindable => {
             info!("[ERROR] Response body error for {}", url);
-            Ok(build_fake_response(StatusCode::NOT_FOUND))
+            Ok(build_fake_response(
+                StatusCode::NOT_FOUND,
+                Outcome::BodyError,
+            ))
+        }
+        Err(e) if e.kind() == ErrorKind::TlsEngine => {
+            info!("[ERROR] {} for {}", e, url);
+            Ok(build_fake_response(
+                StatusCode::from_u16(SSL_CERTIFICATE_ERROR).unwrap(),
+                Outcome::TlsError,
+            ))
         }
-        // This function should not error, so we panic
+        // Anything else still means we couldn't talk to the server, so the link is broken
         Err(e) => {
-            error!(
-                "[ERROR] Found unrecoverable error: {} when accessing {}",
-                e, url
-            );
-            panic!("{}", e)
+            info!("[ERROR] {} for {}", e, url);
+            Ok(build_fake_response(
+                StatusCode::NOT_FOUND,
+                Outcome::ConnectionFailed,
+            ))
         }
     }
 }
 type HostHashMap = HashMap<Host, HashSet<Url>>;
+type SourceHashMap = HashMap<Url, Vec<LinkSource>>;
+/// Where a link was found
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct LinkSource {
+    pub page: Url,
+    /// The link as it was written in the page
+    pub href: String,
+    pub element: String,
+    pub attribute: &'static str,
+    /// Anchor text for links, alt text for images
+    pub text: String,
+    pub rel: Vec<String>,
+    /// Descriptor of a `srcset` candidate
+    pub descriptor: Option<String>,
+}
+impl LinkSource {
+    /// Whether `url` points to the same host as the page it was found in
+    pub fn is_internal(&self, url: &Url) -> bool {
+        self.page.host() == url.host()
+    }
+}
+impl Links {
+    /// Groups the links found in one or more pages by host. Links with schemes we can't check
+    /// are only counted
+    pub fn from_found(found: Vec<(Url, LinkSource)>) -> Self {
+        let mut sources: SourceHashMap = HashMap::new();
+        let mut unchecked: HashSet<Url> = HashSet::new();
+        found.into_iter().for_each(|(url, source)| {
+            if !CHECKED_SCHEMES.contains(&url.scheme()) {
+                unchecked.insert(url);
+                return;
+            }
+            let entry = sources.entry(url).or_default();
+            if !entry.contains(&source) {
+                entry.push(source);
+            }
+        });
+        let mut unchecked_schemes: BTreeMap<String, usize> = BTreeMap::new();
+        for url in unchecked {
+            *unchecked_schemes
+                .entry(url.scheme().to_owned())
+                .or_default() += 1;
+        }
+        let hash_map = get_unique_link_hashmap(sources.keys().collect());
+        Links {
+            hash_map,
+            link_count: sources.len() as u64,
+            sources,
+            unchecked_schemes,
+        }
+    }
+    /// Describes the links that were not checked because of their scheme, e.g. `irc (2), tel (1)`
+    pub fn unchecked_summary(&self) -> Option<String> {
+        if self.unchecked_schemes.is_empty() {
+            return None;
+        }
+        let schemes: Vec<String> = self
+            .unchecked_schemes
+            .iter()
+            .map(|(scheme, count)| format!("{} ({})", scheme, count))
+            .collect();
+        Some(schemes.join(", "))
+    }
+}
 #[derive(Debug)]
 pub struct Links {
     pub hash_map: HostHashMap,
+    /// Maps every unique url to the places it was found in
+    pub sources: SourceHashMap,
     pub link_count: u64,
+    /// How many unique urls of each scheme we can't check were found
+    pub unchecked_schemes: BTreeMap<String, usize>,
+}
+
+/// Print the links that were not checked because of their scheme
+pub fn print_unchecked_schemes(links: &Links) {
+    if let Some(summary) = links.unchecked_summary() {
+        format!("Not checked: {}", summary).print_in_yellow();
+    }
 }
 
 /// Returns a hashmap mapping from root domains to all urls that are related to those domains
 /// For example nintil.com :[nintil.com/a,nintil.com/b]
 /// This is so that we can then turn each into streams and set individual rate limits
+#[allow(clippy::too_many_arguments)]
 pub async fn get_links_from_website(
     client: &HttpClient,
-    user_agent: &str,
+    request: &RequestConfig,
     base_url: &Url,
+    n_par: usize,
     truncate_fragments: bool,
     regex: &Option<Regex>,
+    json_selector: Option<&JsonSelector>,
+    render_command: Option<&str>,
 ) -> Result<Links, RLinksError> {
-    let mut response = request_with_header(client, user_agent, RequestType::Get, base_url)
+    let mut response = request_with_header(client, request, RequestType::Get, base_url)
         .await
         .unwrap();
 
-    match get_status_code_kind(response.status()) {
-        StatusCodeKind::Valid(_) => (),
+    if !request.status.accepts(base_url, response.status()) {
+        return Err(RLinksError::StatusCodeError(
+            response.status(),
+            base_url.to_owned(),
+        ));
+    }
+    let found = match media_type(&response).as_str() {
+        "application/pdf" => {
+            let bytes = response.bytes().await?;
+            get_links_from_pdf(&bytes, base_url, None, truncate_fragments, regex)
+                .unwrap_or_default()
+        }
+        "text/plain" => {
+            let text = response.text().await?;
+            get_links_from_text(&text, base_url, None, truncate_fragments, regex)
+        }
+        json if json == "application/json" || json.ends_with("+json") => {
+            let text = response.text().await?;
+            get_links_from_json(
+                &text,
+                base_url,
+                json_selector,
+                None,
+                truncate_fragments,
+                regex,
+            )?
+        }
         _ => {
-            return Err(RLinksError::StatusCodeError(
-                response.status(),
-                base_url.to_owned(),
-            ))
+            // Pages built by scripts only have their links once the scripts run
+            let text = match render_command {
+                Some(command) => render_page(command, base_url)?,
+                None => response.text().await.unwrap(),
+            };
+            let mut found =
+                get_links_from_page(client, request, &text, base_url, truncate_fragments, regex)
+                    .await;
+            let fetch = |url: Url| async move { fetch_text(client, request, &url).await };
+            found.extend(follow_stylesheets(&found, fetch, None, truncate_fragments, regex).await);
+            let fetch = |url: Url| async move { fetch_pdf(client, request, &url).await };
+            found.extend(follow_pdfs(&found, fetch, n_par, None, truncate_fragments, regex).await);
+            found
         }
-    }
-    let body = Document::from(response.text().await.unwrap().as_str());
-    let links_in_body: Vec<&str> = {
-        let href_links = get_href_links(&body).into_iter();
-        let img_links = get_img_links(&body).into_iter();
-        href_links.chain(img_links).collect()
     };
+    let links = Links::from_found(found);
+    format!("Found {} domains", links.hash_map.len()).print_in_green();
+    Ok(links)
+}
+
+/// The links of an html page or a feed, and of the feeds the page advertises
+async fn get_links_from_page(
+    client: &HttpClient,
+    request: &RequestConfig,
+    text: &str,
+    page: &Url,
+    truncate_fragments: bool,
+    regex: &Option<Regex>,
+) -> Vec<(Url, LinkSource)> {
+    if let Some(found) = get_links_from_feed(text, page, truncate_fragments, regex) {
+        return found;
+    }
+    let body = Document::from(text);
+    let mut found = get_links_from_document(&body, page, None, truncate_fragments, regex);
+    for feed_url in discover_feeds(&body, page) {
+        let feed = fetch_text(client, request, &feed_url)
+            .await
+            .and_then(|xml| get_links_from_feed(&xml, &feed_url, truncate_fragments, regex));
+        found.extend(feed.unwrap_or_default());
+    }
+    found
+}
+
+/// Runs blocking work, such as a lookup through the system resolver, in its own thread so that
+/// other requests carry on in the meantime
+pub async fn run_blocking<T, F>(work: F) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    thread::spawn(move || sender.send(work()));
+    receiver.await.ok()
+}
+
+/// The media type of a response, without parameters
+fn media_type(response: &Response<AsyncBody>) -> String {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// A response we can read the body of, if the url answered with an accepted status code
+async fn fetch_accepted(
+    client: &HttpClient,
+    request: &RequestConfig,
+    url: &Url,
+) -> Option<Response<AsyncBody>> {
+    if !url.scheme().starts_with("http") {
+        return None;
+    }
+    let response = request_with_header(client, request, RequestType::Get, url)
+        .await
+        .ok()?;
+    let failed = response.extensions().get::<Outcome>().is_some();
+    if failed || !request.status.accepts(url, response.status()) {
+        return None;
+    }
+    Some(response)
+}
+
+/// The body of a page we can read, if it answered with an accepted status code
+pub async fn fetch_text(client: &HttpClient, request: &RequestConfig, url: &Url) -> Option<String> {
+    fetch_accepted(client, request, url)
+        .await?
+        .text()
+        .await
+        .ok()
+}
+
+/// The body of a pdf document, if the url answered with one
+pub async fn fetch_pdf(client: &HttpClient, request: &RequestConfig, url: &Url) -> Option<Vec<u8>> {
+    let mut response = fetch_accepted(client, request, url).await?;
+    if media_type(&response) != "application/pdf" {
+        return None;
+    }
+    response.bytes().await.ok()
+}
+
+/// Extracts, resolves and filters the links of a single page, keeping track of where each
+/// one came from. The same url can show up several times
+pub fn get_links_from_document(
+    body: &Document,
+    page: &Url,
+    site_root: Option<&Url>,
+    truncate_fragments: bool,
+    regex: &Option<Regex>,
+) -> Vec<(Url, LinkSource)> {
+    resolve_links(
+        get_raw_links(body),
+        page,
+        site_root,
+        truncate_fragments,
+        regex,
+    )
+}
+
+/// Resolves the links found in `page` and filters out the ones we can't or don't want to check
+pub fn resolve_links(
+    links_in_body: Vec<RawLink<'_>>,
+    page: &Url,
+    site_root: Option<&Url>,
+    truncate_fragments: bool,
+    regex: &Option<Regex>,
+) -> Vec<(Url, LinkSource)> {
     let links_in_body_len = links_in_body.len();
-    let urls_in_body: Vec<Result<Url, RLinksError>> = links_in_body
+    let urls_in_body: Vec<(Result<Url, RLinksError>, RawLink)> = links_in_body
+        .into_iter()
+        .map(|link| {
+            let url = match site_root {
+                Some(site_root) => fix_local_url(link.href, page, site_root),
+                None => fix_malformed_url(link.href, page),
+            };
+            (url, link)
+        })
+        .map(|(result, link)| {
+            (
+                result.map(|mut url| {
+                    if truncate_fragments {
+                        url.set_fragment(None)
+                    };
+                    url
+                }),
+                link,
+            )
+        })
+        .collect();
+    let issues: Vec<HrefIssue> = urls_in_body
         .iter()
-        .map(|link| fix_malformed_url(link, base_url))
-        .map(|result| {
-            result.map(|mut url| {
-                if truncate_fragments {
-                    url.set_fragment(None)
-                };
-                url
+        .filter_map(|(url, link)| {
+            let url = url.as_ref().map_err(ToString::to_string);
+            lint_href(link.href, url, page).map(|(lint, reason)| HrefIssue {
+                page,
+                href: link.href,
+                lint,
+                reason,
             })
         })
         .collect();
-    // We now split the urls by domain
+    print_href_issues(&issues);
     // This valid list links can contain duplicates
-    let valid_urls: Vec<&Url> = urls_in_body
-        .iter()
-        .filter_map(|url| match url {
-            Err(e) => {
-                println!("{}", e);
-                None
-            }
+    let valid_urls: Vec<(Url, RawLink)> = urls_in_body
+        .into_iter()
+        .filter_map(|(url, link)| match url {
+            // Links that don't resolve were reported by the lint pass
+            Err(_) => None,
             Ok(url) => {
-                // If there is no host, it's probably a fake link like javascript:void(0)
-                if url.has_host() {
-                    Some(url)
-                } else {
-                    None
-                }
+                // javascript:void(0) and the like are not links but scripts
+                (url.scheme() != "javascript").then_some((url, link))
             }
         })
         .collect();
     let valid_urls_len = valid_urls.len();
-    let regexed_links: Vec<&Url> = match regex {
+    let regexed_links: Vec<(Url, RawLink)> = match regex {
         Some(r) => valid_urls
             .into_iter()
             // filter for each link searches for link and returns the link if it does not match
-            .filter(|link| !r.is_match(link.as_str()))
+            .filter(|(url, _)| !r.is_match(url.as_str()))
             .collect(),
         None => valid_urls,
     };
     let regexed_links_len = regexed_links.len();
+    let unique_valid_links_len = regexed_links
+        .iter()
+        .map(|(url, _)| url)
+        .collect::<HashSet<&Url>>()
+        .len();
 
-    let unique_valid_links: HashSet<&Url> = HashSet::from_iter(regexed_links);
-    let unique_valid_links_len = unique_valid_links.len();
-
-    println!(
+    print_diagnostic(&format!(
         "Got {} links parsed -> {} are valid -> {} meet regex -> {} unique urls",
         links_in_body_len, valid_urls_len, regexed_links_len, unique_valid_links_len
-    );
-    // This unwrap is safe, every URL has a host
-
-    let hash_map = get_unique_link_hashmap(unique_valid_links);
-    format!("Found {} domains", hash_map.len()).print_in_green();
-
-    Ok(Links {
-        hash_map,
-        link_count: unique_valid_links_len as u64,
-    })
-}
-
-fn get_href_links(body: &Document) -> Vec<&str> {
-    // Unwrapping is safe here as the response has been validated
-    body.find(Name("a"))
-        .filter_map(|n| n.attr("href"))
-        .collect()
-}
-fn get_img_links(body: &Document) -> Vec<&str> {
-    // Unwrapping is safe here as the response has been validated
-    body.find(Name("img"))
-        .filter_map(|n| n.attr("src"))
+    ));
+    regexed_links
+        .into_iter()
+        .map(|(url, link)| {
+            let source = LinkSource {
+                page: page.to_owned(),
+                href: link.href.to_owned(),
+                element: link.element.to_owned(),
+                attribute: link.attribute,
+                text: link.text,
+                rel: link.rel.into_iter().map(str::to_owned).collect(),
+                descriptor: link.descriptor.map(str::to_owned),
+            };
+            (url, source)
+        })
         .collect()
 }
 
 fn get_unique_link_hashmap(unique_valid_links: HashSet<&Url>) -> HostHashMap {
     let mut hash_map: HashMap<Host, HashSet<Url>> = HashMap::new();
     unique_valid_links.into_iter().for_each(|url| {
+        // A file url without a host refers to the local machine
+        let host = url.host().map_or_else(
+            || Host::Domain("localhost".to_owned()),
+            |host| host.to_owned(),
+        );
         hash_map
-            .entry(url.host().unwrap().to_owned())
+            .entry(host)
             .or_insert_with(HashSet::new)
             .insert(url.to_owned());
     });
     hash_map
 }
 
+/// Requests a url with HEAD, and again with GET if the status code says we should
+pub async fn head_or_get(
+    client: &HttpClient,
+    request: &RequestConfig,
+    url: &Url,
+) -> Response<AsyncBody> {
+    let head = request_with_header(client, request, RequestType::Head, url)
+        .await
+        .unwrap();
+    match request.status.kind(url, head.status()) {
+        StatusCodeKind::RetryWithGet => request_with_header(client, request, RequestType::Get, url)
+            .await
+            .unwrap(),
+        StatusCodeKind::Valid | StatusCodeKind::Fail => head,
+    }
+}
+
 /// Request a url trying with both Head and then Get
 async fn is_reachable_url(
     client: &HttpClient,
-    user_agent: &str,
+    request: &RequestConfig,
     url: &Url,
+    sources: &[LinkSource],
     show_ok: bool,
     pbar: &ProgressBar,
-) -> StatusCode {
-    let status = request_with_header(client, user_agent, RequestType::Head, url)
-        .await
-        .unwrap()
-        .status();
-    let r = match get_status_code_kind(status) {
-        StatusCodeKind::Valid(_) => Ok(status),
-        StatusCodeKind::MethodNotAllowed(_) => {
-            match get_status_code_kind(
-                request_with_header(client, user_agent, RequestType::Get, url)
-                    .await
-                    .unwrap()
-                    .status(),
-            ) {
-                StatusCodeKind::Valid(e) => Ok(e),
-                StatusCodeKind::Fail(e) | StatusCodeKind::MethodNotAllowed(e) => {
-                    Err(RLinksError::StatusCodeError(e, url.to_owned()))
+) -> CheckResult {
+    // Embedded urls must be usable as the images, scripts... they are embedded as
+    let kinds: Vec<ResourceKind> = sources.iter().filter_map(ResourceKind::of).collect();
+    // Responsive images can have many candidates, so we say which ones failed
+    let descriptors: Vec<&str> = sources
+        .iter()
+        .filter(|source| source.attribute.ends_with("srcset"))
+        .map(|source| source.descriptor.as_deref().unwrap_or("1x"))
+        .collect();
+    let candidate = if descriptors.is_empty() {
+        String::new()
+    } else {
+        format!(" [srcset {}]", descriptors.join(", "))
+    };
+    // Problems with the addresses of a mailto url
+    let mut problems = vec![];
+    let result = match url.scheme() {
+        "file" => check_file_url(url),
+        "data" => check_data_url(url),
+        "ftp" => check_ftp_url(url).await,
+        "mailto" => {
+            let (result, found) = check_mailto_url(url, request.check_mail_domains).await;
+            problems = found;
+            result
+        }
+        _ => {
+            let start = Instant::now();
+            let response = head_or_get(client, request, url).await;
+            let accepted = request.status.accepts(url, response.status());
+            let mut result = CheckResult::from_response(url, &response, start.elapsed(), accepted);
+            if result.outcome.is_ok() {
+                if let Some(outcome) = check_resource(&response, &kinds, request.max_resource_size)
+                {
+                    result.outcome = outcome;
                 }
             }
+            result
         }
-        StatusCodeKind::Fail(e) => Err(RLinksError::StatusCodeError(e, url.to_owned())),
+    };
+    if result.outcome.is_unchecked() {
+        pbar.println(format!("Could not check {} ({})", url, result.outcome.label()).bold_red());
+    } else if !problems.is_empty() {
+        let pages: Vec<&str> = sources.iter().map(|source| source.page.as_str()).collect();
+        pbar.println(
+            format!(
+                "Invalid address in {} on {}: {}",
+                url,
+                pages.join(", "),
+                problems.join(", ")
+            )
+            .bold_red(),
+        );
+    } else if result.outcome == Outcome::HttpError {
+        let err = RLinksError::StatusCodeError(result.status, url.to_owned());
+        pbar.println(format!("{}{}", err, candidate).bold_red());
+    } else if result.outcome == Outcome::WrongContentType || result.outcome.is_suspect() {
+        pbar.println(
+            format!(
+                "Unexpected response for {} ({}){}",
+                url,
+                result.outcome.label(),
+                candidate
+            )
+            .bold_red(),
+        );
+    } else if result.outcome.is_broken() {
+        pbar.println(
+            format!(
+                "Could not reach {} ({}){}",
+                url,
+                result.outcome.label(),
+                candidate
+            )
+            .bold_red(),
+        );
+    } else if show_ok {
+        pbar.println(format!("Success for {} ({})", url, result.status).bold_green());
     }
-    .map(|response| {
-        if show_ok {
-            pbar.println(format!("Success for {} ({})", url, response).bold_green());
-        }
-        response
-    })
-    .map_err(|err| {
-        pbar.println(format!("{}", err).bold_red());
-        err
-    });
     pbar.inc(1);
-    match r {
-        Ok(e) => e,
-        // TODO: Find a better solution around this
-        Err(_) => StatusCode::NOT_FOUND,
-    }
+    result
 }
-type VectorOfResponses = Vec<StatusCode>;
+type VectorOfResponses = Vec<CheckResult>;
 /// Given a hashmap of domains:urls, make each set of urls into stream, then merge everything into
 /// One big stream, introduce buffering per sub-stream to avoid hammering a domain with requests
 pub async fn make_multiple_requests(
-    links: Links,
+    links: &Links,
     max_domain_concurrency: usize,
     client: &HttpClient,
-    user_agent: &str,
+    request: &RequestConfig,
     show_ok: bool,
 ) -> VectorOfResponses {
     let pbar = ProgressBar::new(links.link_count);
@@ -295,7 +713,10 @@ pub async fn make_multiple_requests(
     pbar.enable_steady_tick(1000);
     let stream_of_streams = links.hash_map.values().map(|values| {
         stream::iter(values.iter())
-            .map(|url| is_reachable_url(client, user_agent, url, show_ok, &pbar))
+            .map(|url| {
+                let sources = links.sources.get(url).map_or(&[][..], Vec::as_slice);
+                is_reachable_url(client, request, url, sources, show_ok, &pbar)
+            })
             .buffer_unordered(max_domain_concurrency)
     });
     let outp = stream::select_all(stream_of_streams).collect().await;
@@ -303,3 +724,102 @@ pub async fn make_multiple_requests(
     pbar.finish_with_message("Finished");
     outp
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use futures::executor::block_on;
+    use isahc::cookies::CookieJar;
+    use url::Url;
+
+    use crate::{
+        cli::{ClientConfig, RequestConfig},
+        headers::ScopedHeaders,
+        outcome::{CheckResult, Outcome},
+        req::{get_client, request_with_header, RequestType},
+    };
+
+    /// A site behind basic auth, with redirects within it and out of it
+    fn serve() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let lines: Vec<String> = BufReader::new(&stream)
+                    .lines()
+                    .map_while(Result::ok)
+                    .take_while(|line| !line.is_empty())
+                    .collect();
+                let path = lines[0].split(' ').nth(1).unwrap_or_default().to_owned();
+                let authorized = lines
+                    .iter()
+                    .any(|line| line.to_lowercase().starts_with("authorization:"));
+                let (status, location) = match path.as_str() {
+                    "/docs" => ("301 Moved Permanently", "/docs/".to_owned()),
+                    "/docs/" if authorized => ("200 OK", String::new()),
+                    "/docs/" => ("401 Unauthorized", String::new()),
+                    "/away" => ("302 Found", format!("http://localhost:{}/public", port)),
+                    "/public" if authorized => ("400 Bad Request", String::new()),
+                    "/public" => ("200 OK", String::new()),
+                    _ => ("302 Found", "/loop".to_owned()),
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status, location
+                );
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_credentialed_redirects() {
+        let port = serve();
+        let request = RequestConfig {
+            user_agent: String::new(),
+            headers: ScopedHeaders::new(
+                vec![],
+                Some("user:pass"),
+                None,
+                vec!["127.0.0.1".to_owned()],
+            )
+            .unwrap(),
+            proxy: Default::default(),
+            tls: Default::default(),
+            status: Default::default(),
+            max_resource_size: None,
+            check_mail_domains: false,
+        };
+        let client = get_client(
+            &ClientConfig {
+                timeout: 5,
+                cookies: None,
+                save_cookies: None,
+            },
+            &CookieJar::new(),
+        );
+        let check = |path: &str| {
+            let url = Url::parse(&format!("http://127.0.0.1:{}{}", port, path)).unwrap();
+            let response = block_on(request_with_header(
+                &client,
+                &request,
+                RequestType::Get,
+                &url,
+            ))
+            .unwrap();
+            CheckResult::from_response(&url, &response, Default::default(), true)
+        };
+
+        let docs = check("/docs");
+        assert_eq!(docs.status.as_u16(), 200);
+        assert_eq!(docs.redirect.unwrap().path(), "/docs/");
+        assert_eq!(check("/away").status.as_u16(), 200);
+        assert_eq!(check("/loop").outcome, Outcome::TooManyRedirects);
+    }
+}
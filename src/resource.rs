@@ -0,0 +1,170 @@
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use isahc::{AsyncBody, Response};
+
+use crate::{error::RLinksError, outcome::Outcome, req::LinkSource};
+
+/// What a page expects to get back when it embeds a url, as opposed to linking to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResourceKind {
+    Image,
+    Script,
+    Stylesheet,
+}
+
+impl ResourceKind {
+    pub fn of(source: &LinkSource) -> Option<Self> {
+        match source.element.as_str() {
+            // Urls in a style attribute aren't what the element itself loads
+            _ if source.attribute == "style" => None,
+            "css" | "style" if source.attribute == "@import" => Some(ResourceKind::Stylesheet),
+            "img" => Some(ResourceKind::Image),
+            "meta" if source.text.ends_with(":image") || source.text.contains(":image:") => {
+                Some(ResourceKind::Image)
+            }
+            "link"
+                if source
+                    .rel
+                    .iter()
+                    .any(|rel| rel.to_ascii_lowercase().ends_with("icon")) =>
+            {
+                Some(ResourceKind::Image)
+            }
+            "source" | "link" if source.attribute.ends_with("srcset") => Some(ResourceKind::Image),
+            "script" => Some(ResourceKind::Script),
+            "link"
+                if source
+                    .rel
+                    .iter()
+                    .any(|rel| rel.eq_ignore_ascii_case("stylesheet")) =>
+            {
+                Some(ResourceKind::Stylesheet)
+            }
+            _ => None,
+        }
+    }
+    /// Whether a response with this media type (without parameters) can be used as the resource
+    fn accepts(self, media_type: &str) -> bool {
+        match self {
+            ResourceKind::Image => media_type.starts_with("image/"),
+            ResourceKind::Script => matches!(
+                media_type,
+                "text/javascript"
+                    | "application/javascript"
+                    | "application/x-javascript"
+                    | "application/ecmascript"
+                    | "text/ecmascript"
+            ),
+            ResourceKind::Stylesheet => media_type == "text/css",
+        }
+    }
+}
+
+/// Parses a size such as `500k`, `5MB` or `1G`. A plain number is a number of bytes
+pub fn parse_size(size: &str) -> Result<u64, RLinksError> {
+    let invalid = || RLinksError::SizeParseError(size.to_owned());
+    let lower = size.trim().to_lowercase();
+    let (number, unit) = match lower.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => lower.split_at(i),
+        None => (lower.as_str(), ""),
+    };
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let multiplier = match unit.trim() {
+        "" | "b" => 1,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        _ => return Err(invalid()),
+    };
+    number.checked_mul(multiplier).ok_or_else(invalid)
+}
+
+/// Checks that a response can be used as every kind of resource the url is embedded as. A
+/// response that doesn't say what it is gets the benefit of the doubt
+pub fn check_resource(
+    response: &Response<AsyncBody>,
+    kinds: &[ResourceKind],
+    max_size: Option<u64>,
+) -> Option<Outcome> {
+    if kinds.is_empty() {
+        return None;
+    }
+    let media_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase()
+        });
+    if let Some(media_type) = media_type {
+        if !kinds.iter().all(|kind| kind.accepts(&media_type)) {
+            return Some(Outcome::WrongContentType);
+        }
+    }
+    let length = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    match (length, max_size) {
+        (Some(length), Some(max_size)) if length > max_size => Some(Outcome::TooLarge),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use isahc::{AsyncBody, Response};
+
+    use crate::{
+        outcome::Outcome,
+        resource::{check_resource, parse_size, ResourceKind},
+    };
+
+    fn response(content_type: &str, length: u64) -> Response<AsyncBody> {
+        Response::builder()
+            .header("content-type", content_type)
+            .header("content-length", length)
+            .body(AsyncBody::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_check_resource() {
+        let image = [ResourceKind::Image];
+        assert_eq!(
+            check_resource(&response("image/png", 10), &image, None),
+            None
+        );
+        assert_eq!(
+            check_resource(&response("text/html; charset=utf-8", 10), &image, None),
+            Some(Outcome::WrongContentType)
+        );
+        assert_eq!(
+            check_resource(
+                &response("text/javascript; charset=utf-8", 10),
+                &[ResourceKind::Script],
+                None
+            ),
+            None
+        );
+        assert_eq!(
+            check_resource(&response("text/css", 10), &[ResourceKind::Stylesheet], None),
+            None
+        );
+        assert_eq!(
+            check_resource(&response("image/jpeg", 2048), &image, Some(1024)),
+            Some(Outcome::TooLarge)
+        );
+        assert_eq!(check_resource(&response("text/html", 10), &[], None), None);
+        assert_eq!(parse_size("5MB").unwrap(), 5 << 20);
+        assert_eq!(parse_size("500k").unwrap(), 500 << 10);
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert!(parse_size("5 parsecs").is_err());
+        assert!(parse_size("18446744073709551615gb").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures::{stream, StreamExt};
+use http::header::CONTENT_TYPE;
+use isahc::{prelude::*, HttpClient};
+use select::{document::Document, predicate::Name};
+use url::Url;
+
+use crate::{
+    cli::RequestConfig,
+    outcome::{CheckResult, Outcome},
+    req::{request_with_header, RequestType},
+    text::ColorsExt,
+};
+
+// Phrases that error pages put in their title or heading
+const NOT_FOUND_PHRASES: [&str; 6] = [
+    "not found",
+    "404",
+    "does not exist",
+    "doesn't exist",
+    "no longer available",
+    "page unavailable",
+];
+// How much of its text a page must share with the probe to look like the same error page
+const SIMILARITY_THRESHOLD: f64 = 0.9;
+
+/// The text of a page we fetched, and where we ended up
+struct Page {
+    url: Url,
+    headings: Vec<String>,
+    words: HashSet<String>,
+}
+
+impl Page {
+    /// Fetches an html page. Anything else can't be a soft 404
+    async fn fetch(client: &HttpClient, request: &RequestConfig, url: &Url) -> Option<Page> {
+        let mut response = request_with_header(client, request, RequestType::Get, url)
+            .await
+            .ok()?;
+        let is_html = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("html"));
+        if !response.status().is_success() || !is_html {
+            return None;
+        }
+        let body = response.text().await.ok()?;
+        let document = Document::from(body.as_str());
+        let headings = document
+            .find(Name("title"))
+            .chain(document.find(Name("h1")))
+            .map(|node| node.text().to_lowercase())
+            .collect();
+        let words = document
+            .find(Name("body"))
+            .flat_map(|node| {
+                node.text()
+                    .split_whitespace()
+                    .map(str::to_lowercase)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let effective = response
+            .effective_uri()
+            .and_then(|uri| Url::parse(&uri.to_string()).ok())
+            .unwrap_or_else(|| url.to_owned());
+        Some(Page {
+            url: effective,
+            headings,
+            words,
+        })
+    }
+    /// Jaccard similarity of the words of both pages
+    fn similarity(&self, other: &Page) -> f64 {
+        let union = self.words.union(&other.words).count();
+        if union == 0 {
+            return 1.0;
+        }
+        self.words.intersection(&other.words).count() as f64 / union as f64
+    }
+}
+
+/// A path no site should have, so that whatever the host answers is its error page
+fn probe_url(url: &Url) -> Url {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let mut probe = url.clone();
+    probe.set_path(&format!("/rlinks-probe-{:x}", nanos));
+    probe.set_query(None);
+    probe.set_fragment(None);
+    probe
+}
+
+/// Why a page that answered 200 looks like an error page, if it does
+fn soft_404_reason(url: &Url, page: &Page, probe: Option<&Page>) -> Option<&'static str> {
+    if url.path() != "/" && page.url.path() == "/" && page.url.host() == url.host() {
+        return Some("redirects to the site root");
+    }
+    if page
+        .headings
+        .iter()
+        .any(|heading| NOT_FOUND_PHRASES.iter().any(|p| heading.contains(p)))
+    {
+        return Some("the page says it was not found");
+    }
+    match probe {
+        Some(probe) if page.similarity(probe) >= SIMILARITY_THRESHOLD => {
+            Some("looks like the page of a path that doesn't exist")
+        }
+        _ => None,
+    }
+}
+
+/// Fetches every page that was reported as fine and flags the ones that look like error pages.
+/// Each host is probed once with a made-up path: if it answers that with a page too, pages that
+/// look just like it are suspected as well
+pub async fn detect_soft_404s(
+    results: &mut [CheckResult],
+    client: &HttpClient,
+    request: &RequestConfig,
+    max_domain_concurrency: usize,
+) {
+    let mut by_host: HashMap<String, Vec<&mut CheckResult>> = HashMap::new();
+    results
+        .iter_mut()
+        .filter(|result| result.outcome.is_ok() && result.url.scheme().starts_with("http"))
+        .for_each(|result| {
+            let host = result.url.host_str().unwrap_or_default().to_owned();
+            by_host.entry(host).or_default().push(result);
+        });
+    let hosts = by_host.into_values().map(|results| async move {
+        let probe = Page::fetch(client, request, &probe_url(&results[0].url)).await;
+        let probe = &probe;
+        stream::iter(results)
+            .for_each_concurrent(max_domain_concurrency, |result| async move {
+                let page = match Page::fetch(client, request, &result.url).await {
+                    Some(page) => page,
+                    None => return,
+                };
+                if let Some(reason) = soft_404_reason(&result.url, &page, probe.as_ref()) {
+                    format!("Suspected soft 404 for {} ({})", result.url, reason).print_in_yellow();
+                    result.outcome = Outcome::SuspectedSoft404;
+                }
+            })
+            .await
+    });
+    stream::iter(hosts)
+        .for_each_concurrent(None, |host| host)
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::soft404::{soft_404_reason, Page};
+
+    fn page(url: &str, headings: &[&str], words: &str) -> Page {
+        Page {
+            url: Url::parse(url).unwrap(),
+            headings: headings.iter().map(|h| h.to_string()).collect(),
+            words: words.split_whitespace().map(str::to_owned).collect(),
+        }
+    }
+
+    #[test]
+    fn test_soft_404_reason() {
+        let url = Url::parse("https://example.com/docs/old").unwrap();
+        let probe = page(
+            "https://example.com/rlinks-probe",
+            &["example"],
+            "sorry we could not find that",
+        );
+        assert!(soft_404_reason(&url, &page("https://example.com/", &[], "home"), None).is_some());
+        assert!(soft_404_reason(
+            &url,
+            &page(url.as_str(), &["page not found - example"], "hi"),
+            None
+        )
+        .is_some());
+        assert!(soft_404_reason(
+            &url,
+            &page(url.as_str(), &["example"], "sorry we could not find that"),
+            Some(&probe)
+        )
+        .is_some());
+        assert!(soft_404_reason(
+            &url,
+            &page(url.as_str(), &["docs"], "installing the tool is easy"),
+            Some(&probe)
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,228 @@
+use std::{collections::HashMap, fs::File, io::BufReader, path::Path};
+
+use http::StatusCode;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{error::RLinksError, url_fix::host_matches};
+
+/// An inclusive range of status codes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusRange {
+    start: u16,
+    end: u16,
+}
+
+impl StatusRange {
+    fn contains(&self, status: StatusCode) -> bool {
+        (self.start..=self.end).contains(&status.as_u16())
+    }
+}
+
+/// Parses a comma separated list of codes and ranges, such as `200..=299,403,999`. Ranges are
+/// written as in Rust, so `200..300` doesn't include 300
+pub fn parse_ranges(ranges: &str) -> Result<Vec<StatusRange>, RLinksError> {
+    let invalid = || RLinksError::StatusRangeParseError(ranges.to_owned());
+    let code = |code: &str| code.trim().parse::<u16>().map_err(|_| invalid());
+    ranges
+        .split(',')
+        .map(str::trim)
+        .filter(|range| !range.is_empty())
+        .map(|range| {
+            let (start, end) = if let Some((start, end)) = range.split_once("..=") {
+                (code(start)?, code(end)?)
+            } else if let Some((start, end)) = range.split_once("..") {
+                (code(start)?, code(end)?.checked_sub(1).ok_or_else(invalid)?)
+            } else {
+                (code(range)?, code(range)?)
+            };
+            if start > end || !(100..=999).contains(&start) || end > 999 {
+                return Err(invalid());
+            }
+            Ok(StatusRange { start, end })
+        })
+        .collect()
+}
+
+fn parse_optional(ranges: Option<&str>) -> Result<Option<Vec<StatusRange>>, RLinksError> {
+    ranges.map(parse_ranges).transpose()
+}
+
+/// What to do with the status code of a response
+#[derive(Debug, PartialEq)]
+pub enum StatusCodeKind {
+    Valid,
+    /// The server may not handle HEAD properly, so we ask again with GET
+    RetryWithGet,
+    Fail,
+}
+
+/// The status codes we accept and the ones that make us retry a HEAD request with GET
+#[derive(Debug, Clone, PartialEq)]
+struct StatusRules {
+    accept: Vec<StatusRange>,
+    get_fallback: Vec<StatusRange>,
+}
+
+/// How a config file can override the rules, globally or for some hosts
+#[derive(Deserialize, Default)]
+struct RulesFile {
+    accept: Option<String>,
+    get_fallback: Option<String>,
+}
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(flatten)]
+    rules: RulesFile,
+    #[serde(default)]
+    hosts: HashMap<String, RulesFile>,
+}
+
+/// Which status codes count as success, globally and for each host
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusPolicy {
+    rules: StatusRules,
+    /// Hosts with their own rules. Subdomains are included
+    hosts: Vec<(String, StatusRules)>,
+}
+
+impl Default for StatusPolicy {
+    /// Success is any 2xx or 3xx, and only 405 makes us retry with GET
+    fn default() -> Self {
+        StatusPolicy {
+            rules: StatusRules {
+                accept: vec![StatusRange {
+                    start: 200,
+                    end: 399,
+                }],
+                get_fallback: vec![StatusRange {
+                    start: 405,
+                    end: 405,
+                }],
+            },
+            hosts: vec![],
+        }
+    }
+}
+
+impl StatusPolicy {
+    /// Rules given in the command line take precedence over those in the config file
+    pub fn new(
+        accept: Option<&str>,
+        get_fallback: Option<&str>,
+        config: Option<&Path>,
+    ) -> Result<Self, RLinksError> {
+        let config: ConfigFile = match config {
+            Some(path) => serde_json::from_reader(BufReader::new(File::open(path)?))?,
+            None => ConfigFile::default(),
+        };
+        let defaults = StatusPolicy::default().rules;
+        let rules = StatusRules {
+            accept: parse_optional(accept.or(config.rules.accept.as_deref()))?
+                .unwrap_or(defaults.accept),
+            get_fallback: parse_optional(get_fallback.or(config.rules.get_fallback.as_deref()))?
+                .unwrap_or(defaults.get_fallback),
+        };
+        let hosts = config
+            .hosts
+            .into_iter()
+            .map(|(host, overrides)| {
+                let host_rules = StatusRules {
+                    accept: parse_optional(overrides.accept.as_deref())?
+                        .unwrap_or_else(|| rules.accept.clone()),
+                    get_fallback: parse_optional(overrides.get_fallback.as_deref())?
+                        .unwrap_or_else(|| rules.get_fallback.clone()),
+                };
+                Ok((host, host_rules))
+            })
+            .collect::<Result<_, RLinksError>>()?;
+        Ok(StatusPolicy { rules, hosts })
+    }
+    /// The rules of the most specific host that matches, or the global ones
+    fn rules_for(&self, url: &Url) -> &StatusRules {
+        let host = url.host_str().unwrap_or_default();
+        self.hosts
+            .iter()
+            .filter(|(pattern, _)| host_matches(host, pattern))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map_or(&self.rules, |(_, rules)| rules)
+    }
+    pub fn accepts(&self, url: &Url, status: StatusCode) -> bool {
+        self.rules_for(url)
+            .accept
+            .iter()
+            .any(|range| range.contains(status))
+    }
+    /// What to do with the response to a HEAD request
+    pub fn kind(&self, url: &Url, status: StatusCode) -> StatusCodeKind {
+        let rules = self.rules_for(url);
+        if rules
+            .get_fallback
+            .iter()
+            .any(|range| range.contains(status))
+        {
+            StatusCodeKind::RetryWithGet
+        } else if rules.accept.iter().any(|range| range.contains(status)) {
+            StatusCodeKind::Valid
+        } else {
+            StatusCodeKind::Fail
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use http::StatusCode;
+    use tempfile::NamedTempFile;
+    use url::Url;
+
+    use crate::status::{parse_ranges, StatusCodeKind, StatusPolicy};
+
+    #[test]
+    fn test_parse_ranges() {
+        let ranges = parse_ranges("200..=299, 403,999,300..304").unwrap();
+        assert_eq!(ranges.len(), 4);
+        assert_eq!((ranges[3].start, ranges[3].end), (300, 303));
+        assert!(parse_ranges("200..=abc").is_err());
+        assert!(parse_ranges("299..=200").is_err());
+        assert!(parse_ranges("1000").is_err());
+    }
+
+    #[test]
+    fn test_host_overrides() {
+        let file = NamedTempFile::new().unwrap();
+        fs::write(
+            file.path(),
+            r#"{"get_fallback": "403,405", "hosts": {"linkedin.com": {"accept": "200..=399,999"}}}"#,
+        )
+        .unwrap();
+        let policy = StatusPolicy::new(Some("200..=299"), None, Some(file.path())).unwrap();
+        let kind = |url: &str, code: u16| {
+            policy.kind(
+                &Url::parse(url).unwrap(),
+                StatusCode::from_u16(code).unwrap(),
+            )
+        };
+        assert_eq!(kind("https://example.com/", 200), StatusCodeKind::Valid);
+        assert_eq!(kind("https://example.com/", 301), StatusCodeKind::Fail);
+        assert_eq!(kind("https://example.com/", 999), StatusCodeKind::Fail);
+        assert_eq!(
+            kind("https://example.com/", 403),
+            StatusCodeKind::RetryWithGet
+        );
+        assert_eq!(
+            kind("https://www.linkedin.com/in/a", 999),
+            StatusCodeKind::Valid
+        );
+        assert_eq!(
+            kind("https://www.linkedin.com/in/a", 301),
+            StatusCodeKind::Valid
+        );
+        assert_eq!(
+            kind("https://www.linkedin.com/in/a", 403),
+            StatusCodeKind::RetryWithGet
+        );
+    }
+}
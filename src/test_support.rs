@@ -0,0 +1,33 @@
+use http::StatusCode;
+use url::Url;
+
+use crate::{
+    outcome::{CheckResult, Outcome},
+    req::LinkSource,
+};
+
+/// Where a link to `href` in `element` of `page` was found. Tests that care about the text or
+/// anything else fill it in on top of this
+pub fn source(page: &Url, href: &str, element: &str, rel: &[&str]) -> LinkSource {
+    LinkSource {
+        page: page.clone(),
+        href: href.to_owned(),
+        element: element.to_owned(),
+        attribute: match element {
+            "a" | "link" => "href",
+            _ => "src",
+        },
+        text: String::new(),
+        rel: rel.iter().map(|rel| rel.to_string()).collect(),
+        descriptor: None,
+    }
+}
+
+/// A link as the extractors return it, resolved against the page it is in
+pub fn found(page: &Url, href: &str, element: &str, rel: &[&str]) -> (Url, LinkSource) {
+    (page.join(href).unwrap(), source(page, href, element, rel))
+}
+
+pub fn result(url: &str, status: StatusCode, outcome: Outcome) -> CheckResult {
+    CheckResult::new(&Url::parse(url).unwrap(), status, outcome)
+}
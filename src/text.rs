@@ -1,9 +1,29 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use console::style;
+
+/// Set when stdout carries a machine-readable report, so that nothing else ends up mixed into it
+static DIAGNOSTICS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+pub fn send_diagnostics_to_stderr() {
+    DIAGNOSTICS_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// Prints progress and diagnostics, which go to stderr when stdout carries a report
+pub fn print_diagnostic(line: &str) {
+    if DIAGNOSTICS_TO_STDERR.load(Ordering::Relaxed) {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
 pub trait ColorsExt {
     fn bold_red(&self) -> String;
     fn bold_green(&self) -> String;
     fn print_in_green(&self);
     fn print_in_red(&self);
+    fn print_in_yellow(&self);
 }
 impl ColorsExt for str {
     fn bold_red(self: &str) -> String {
@@ -13,9 +33,12 @@ impl ColorsExt for str {
         format!("{}", style(self).green().bold())
     }
     fn print_in_green(self: &str) {
-        println!("{}", self.bold_green());
+        print_diagnostic(&self.bold_green());
     }
     fn print_in_red(self: &str) {
-        println!("{}", self.bold_red());
+        print_diagnostic(&self.bold_red());
+    }
+    fn print_in_yellow(self: &str) {
+        print_diagnostic(&style(self).yellow().bold().to_string());
     }
 }
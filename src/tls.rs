@@ -0,0 +1,342 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fs,
+    io::Write,
+    net::TcpStream,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use http::request::Builder;
+use isahc::{
+    config::{CaCertificate, ClientCertificate, Configurable, PrivateKey, SslOption},
+    error::ErrorKind,
+};
+use openssl::{
+    asn1::Asn1Time,
+    ssl::{
+        HandshakeError, MidHandshakeSslStream, SslConnector, SslMethod, SslStream, SslVerifyMode,
+    },
+    x509::X509VerifyResult,
+};
+use serde::Serialize;
+use tempfile::{NamedTempFile, TempPath};
+use url::Url;
+
+use crate::{
+    cli::RequestConfig,
+    error::RLinksError,
+    outcome::{CheckResult, Outcome},
+    req::run_blocking,
+    text::ColorsExt,
+    url_fix::host_matches,
+};
+
+type SslStreamResult = Result<SslStream<TcpStream>, MidHandshakeSslStream<TcpStream>>;
+
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(10);
+const SECONDS_PER_DAY: i64 = 60 * 60 * 24;
+// openssl-sys has these, but it isn't worth depending on it for a few constants
+const X509_V_ERR_UNABLE_TO_GET_ISSUER_CERT: i32 = 2;
+const X509_V_ERR_CERT_HAS_EXPIRED: i32 = 10;
+const X509_V_ERR_DEPTH_ZERO_SELF_SIGNED_CERT: i32 = 18;
+const X509_V_ERR_SELF_SIGNED_CERT_IN_CHAIN: i32 = 19;
+const X509_V_ERR_UNABLE_TO_GET_ISSUER_CERT_LOCALLY: i32 = 20;
+const X509_V_ERR_UNABLE_TO_VERIFY_LEAF_SIGNATURE: i32 = 21;
+const X509_V_ERR_HOSTNAME_MISMATCH: i32 = 62;
+const X509_V_ERR_IP_ADDRESS_MISMATCH: i32 = 64;
+
+/// Certificates to trust and present, and hosts whose certificates are not verified at all
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    /// Extra CA bundle, for hosts signed by an internal authority
+    pub ca_cert: Option<PathBuf>,
+    /// The system authorities followed by those in `ca_cert`, which is the bundle curl is given
+    pub ca_bundle: Option<Arc<TempPath>>,
+    /// PEM client certificate and its key, if the key is not in the same file
+    pub client_cert: Option<(PathBuf, Option<PathBuf>)>,
+    pub insecure_hosts: Vec<String>,
+    /// Why the certificate of each host was rejected, so that we only connect to find out once
+    pub diagnosed: Arc<Mutex<HashMap<String, Outcome>>>,
+}
+
+impl TlsConfig {
+    fn is_insecure(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default();
+        self.insecure_hosts
+            .iter()
+            .any(|insecure| host_matches(host, insecure))
+    }
+    /// The client certificate is a credential, so it is only presented when `send_credentials`
+    pub fn apply(&self, builder: Builder, url: &Url, send_credentials: bool) -> Builder {
+        let builder = match &self.ca_bundle {
+            Some(path) => builder.ssl_ca_certificate(CaCertificate::file(path.to_path_buf())),
+            None => builder,
+        };
+        let builder = match &self.client_cert {
+            Some((cert, key)) if send_credentials => {
+                let key = key.as_ref().map(|key| PrivateKey::pem_file(key, None));
+                builder.ssl_client_certificate(ClientCertificate::pem_file(cert, key))
+            }
+            _ => builder,
+        };
+        if self.is_insecure(url) {
+            builder.ssl_options(
+                SslOption::DANGER_ACCEPT_INVALID_CERTS | SslOption::DANGER_ACCEPT_INVALID_HOSTS,
+            )
+        } else {
+            builder
+        }
+    }
+}
+
+/// curl trusts only the bundle it is given, while openssl adds a bundle to the system one. To trust
+/// the same authorities in both, curl gets a copy of the system bundle with `ca_cert` appended,
+/// which is removed once we are done
+pub fn extend_system_bundle(ca_cert: &Path) -> Result<TempPath, RLinksError> {
+    let mut bundle = NamedTempFile::new()?;
+    if let Some(system) = openssl_probe::probe().cert_file {
+        bundle.write_all(&fs::read(system)?)?;
+        bundle.write_all(b"\n")?;
+    }
+    bundle.write_all(&fs::read(ca_cert)?)?;
+    Ok(bundle.into_temp_path())
+}
+
+/// curl only tells us that a certificate was rejected, so we connect again with openssl to find
+/// out why. That connection blocks, so it runs in its own thread, and only once per host
+pub async fn classify_tls_error(error: &isahc::Error, url: &Url, config: &TlsConfig) -> Outcome {
+    if error.kind() == ErrorKind::BadClientCertificate {
+        return Outcome::ClientCertificateError;
+    }
+    let key = match host_key(url) {
+        Some(key) => key,
+        None => return Outcome::TlsError,
+    };
+    if let Some(outcome) = config.diagnosed.lock().unwrap().get(&key) {
+        return *outcome;
+    }
+    let (url, ca_cert) = (url.clone(), config.ca_cert.clone());
+    let outcome = run_blocking(move || diagnose_certificate(&url, ca_cert.as_deref()))
+        .await
+        .unwrap_or(Outcome::TlsError);
+    config.diagnosed.lock().unwrap().insert(key, outcome);
+    outcome
+}
+
+fn diagnose_certificate(url: &Url, ca_cert: Option<&Path>) -> Outcome {
+    match verify_certificate(url, ca_cert) {
+        Some(result) => match result.as_raw() {
+            X509_V_ERR_CERT_HAS_EXPIRED => Outcome::CertificateExpired,
+            X509_V_ERR_DEPTH_ZERO_SELF_SIGNED_CERT | X509_V_ERR_SELF_SIGNED_CERT_IN_CHAIN => {
+                Outcome::CertificateSelfSigned
+            }
+            X509_V_ERR_HOSTNAME_MISMATCH | X509_V_ERR_IP_ADDRESS_MISMATCH => {
+                Outcome::CertificateHostMismatch
+            }
+            X509_V_ERR_UNABLE_TO_GET_ISSUER_CERT_LOCALLY
+            | X509_V_ERR_UNABLE_TO_GET_ISSUER_CERT
+            | X509_V_ERR_UNABLE_TO_VERIFY_LEAF_SIGNATURE => Outcome::CertificateUntrusted,
+            _ => Outcome::TlsError,
+        },
+        None => Outcome::TlsError,
+    }
+}
+
+/// Opens a tls connection to the host in `url`, checking its certificate unless `verify` is false
+fn connect(url: &Url, ca_cert: Option<&Path>, verify: bool) -> Option<SslStreamResult> {
+    let host = url.host_str()?;
+    let address = url.socket_addrs(|| Some(443)).ok()?.into_iter().next()?;
+    let stream = TcpStream::connect_timeout(&address, VERIFY_TIMEOUT).ok()?;
+    stream.set_read_timeout(Some(VERIFY_TIMEOUT)).ok()?;
+    let mut builder = SslConnector::builder(SslMethod::tls()).ok()?;
+    if let Some(ca_cert) = ca_cert {
+        builder.set_ca_file(ca_cert).ok()?;
+    }
+    if !verify {
+        builder.set_verify(SslVerifyMode::NONE);
+    }
+    // IPv6 hosts come in brackets, which openssl doesn't expect
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match builder.build().connect(host, stream) {
+        Ok(stream) => Some(Ok(stream)),
+        Err(HandshakeError::Failure(stream)) => Some(Err(stream)),
+        Err(_) => None,
+    }
+}
+
+/// Result of verifying the certificate of the host in `url`, if we could connect to it
+fn verify_certificate(url: &Url, ca_cert: Option<&Path>) -> Option<X509VerifyResult> {
+    match connect(url, ca_cert, true)? {
+        Ok(stream) => Some(stream.ssl().verify_result()),
+        Err(stream) => Some(stream.ssl().verify_result()),
+    }
+}
+
+/// When the leaf certificate of a host stops being valid
+#[derive(Debug, Clone, Serialize)]
+pub struct CertificateExpiry {
+    pub host: String,
+    /// As openssl prints it, e.g. `Dec 31 23:59:59 2026 GMT`
+    pub expires: String,
+    /// Negative once the certificate has expired
+    pub days_left: i64,
+    /// Whether it expires within the warning window
+    pub expiring: bool,
+}
+
+/// Fetches the leaf certificate of the host in `url`, even if it doesn't verify, and reads its
+/// expiry date
+fn certificate_expiry(
+    url: &Url,
+    config: &TlsConfig,
+    window: Duration,
+) -> Option<CertificateExpiry> {
+    let stream = connect(url, config.ca_cert.as_deref(), false)?.ok()?;
+    let certificate = stream.ssl().peer_certificate()?;
+    let left = Asn1Time::days_from_now(0)
+        .ok()?
+        .diff(certificate.not_after())
+        .ok()?;
+    let seconds_left = i64::from(left.days) * SECONDS_PER_DAY + i64::from(left.secs);
+    Some(CertificateExpiry {
+        host: host_key(url)?,
+        expires: certificate.not_after().to_string(),
+        days_left: seconds_left.div_euclid(SECONDS_PER_DAY),
+        expiring: seconds_left < window.as_secs() as i64,
+    })
+}
+
+/// Hosts are told apart by their port too, which is left out when it is the default one
+pub fn host_key(url: &Url) -> Option<String> {
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_owned(),
+    })
+}
+
+/// Looks up the certificate expiry of every https host we checked a link in, keyed by host.
+/// isahc doesn't hand us the certificates curl saw, so we open a connection of our own to each
+/// host, `n_par` at a time. Hosts behind a proxy are skipped, as we may not reach them directly
+pub fn certificate_expiries(
+    results: &[CheckResult],
+    request: &RequestConfig,
+    n_par: usize,
+    window: Duration,
+) -> BTreeMap<String, CertificateExpiry> {
+    let mut hosts: BTreeMap<String, &Url> = BTreeMap::new();
+    results
+        .iter()
+        .map(|result| &result.url)
+        .filter(|url| url.scheme() == "https" && request.proxy.proxy_for(url).is_none())
+        .for_each(|url| {
+            if let Some(key) = host_key(url) {
+                hosts.entry(key).or_insert(url);
+            }
+        });
+    let urls: Vec<&Url> = hosts.into_values().collect();
+    let mut expiries = BTreeMap::new();
+    for chunk in urls.chunks(n_par.max(1)) {
+        let found: Vec<Option<CertificateExpiry>> = thread::scope(|scope| {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|url| scope.spawn(move || certificate_expiry(url, &request.tls, window)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().ok().flatten())
+                .collect()
+        });
+        found.into_iter().flatten().for_each(|expiry| {
+            expiries.insert(expiry.host.clone(), expiry);
+        });
+    }
+    expiries
+}
+
+pub fn print_expiry_warnings(expiries: &BTreeMap<String, CertificateExpiry>) {
+    expiries
+        .values()
+        .filter(|expiry| expiry.expiring)
+        .for_each(|expiry| {
+            if expiry.days_left < 0 {
+                format!(
+                    "Certificate for {} expired on {}",
+                    expiry.host, expiry.expires
+                )
+                .print_in_red()
+            } else {
+                format!(
+                    "Certificate for {} expires on {} ({} days left)",
+                    expiry.host, expiry.expires, expiry.days_left
+                )
+                .print_in_yellow()
+            }
+        });
+}
+
+/// Parses a duration such as `30d`, `12h` or `2w`. A plain number is a number of days
+pub fn parse_window(window: &str) -> Result<Duration, RLinksError> {
+    let invalid = || RLinksError::DurationParseError(window.to_owned());
+    let window = window.trim();
+    let (number, unit) = match window.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => window.split_at(i),
+        None => (window, "d"),
+    };
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        _ => return Err(invalid()),
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+/// isahc files failed handshakes under failed connections, so we look at curl's error for those
+pub fn is_tls_error(error: &isahc::Error) -> bool {
+    match error.kind() {
+        ErrorKind::BadServerCertificate | ErrorKind::BadClientCertificate => true,
+        ErrorKind::ConnectionFailed => error
+            .source()
+            .and_then(|source| source.downcast_ref::<curl::Error>())
+            .is_some_and(curl::Error::is_ssl_connect_error),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::tls::parse_window;
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(
+            parse_window("30d").unwrap(),
+            Duration::from_secs(30 * 24 * 3600)
+        );
+        assert_eq!(parse_window("12h").unwrap(), Duration::from_secs(12 * 3600));
+        assert_eq!(
+            parse_window("2w").unwrap(),
+            Duration::from_secs(14 * 24 * 3600)
+        );
+        assert_eq!(
+            parse_window("7").unwrap(),
+            Duration::from_secs(7 * 24 * 3600)
+        );
+        assert!(parse_window("d").is_err());
+        assert!(parse_window("3 months").is_err());
+        assert!(parse_window("18446744073709551615w").is_err());
+    }
+}
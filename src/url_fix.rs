@@ -13,22 +13,33 @@ pub fn add_http(url_string: &str) -> Result<Url, RLinksError> {
 }
 /// This fixes a relative link to a potential URL
 pub fn fix_malformed_url(x: &str, base_url: &Url) -> Result<Url, RLinksError> {
-    // We can skip some patterns
-    if x.starts_with("irc://") {
-        return Err(RLinksError::IgnoredPattern(
-            x.to_owned(),
-            "irc://".to_owned(),
-        ));
-    }
     // Links that have fragments can be treated as the same link, as they don't affect checking
     base_url.join(x).map_err(RLinksError::UrlParseError)
 }
+/// Like `fix_malformed_url`, but root-relative links (`/about`) are resolved against the root of a
+/// local site instead of the root of the filesystem. Protocol-relative links (`//host/a`) point to
+/// the web, so they get https rather than the `file:` scheme of the page
+pub fn fix_local_url(x: &str, page: &Url, site_root: &Url) -> Result<Url, RLinksError> {
+    match x.strip_prefix('/') {
+        Some(path) if path.starts_with('/') => fix_malformed_url(&format!("https:{}", x), page),
+        Some(path) => fix_malformed_url(path, site_root),
+        None => fix_malformed_url(x, page),
+    }
+}
+/// Whether `host` is `pattern` or one of its subdomains. A `*` pattern matches every host
+pub fn host_matches(host: &str, pattern: &str) -> bool {
+    pattern == "*"
+        || host == pattern
+        || host
+            .strip_suffix(pattern)
+            .is_some_and(|sub| sub.ends_with('.'))
+}
 
 #[cfg(test)]
 mod tests {
     use url::Url;
 
-    use crate::url_fix::{add_http, fix_malformed_url};
+    use crate::url_fix::{add_http, fix_local_url, fix_malformed_url, host_matches};
 
     #[test]
     fn test_add_http() {
@@ -80,5 +91,44 @@ mod tests {
             .to_string(),
             "https://en.wikipedia.org/wiki/Phoney_War"
         );
+        assert_eq!(
+            fix_malformed_url("irc://irc.libera.chat/rust", &base_url)
+                .unwrap()
+                .to_string(),
+            "irc://irc.libera.chat/rust"
+        );
+    }
+
+    #[test]
+    fn test_fix_local_url() {
+        let site_root = Url::parse("file:///site/public/").unwrap();
+        let page = Url::parse("file:///site/public/docs/index.html").unwrap();
+        assert_eq!(
+            fix_local_url("/about/", &page, &site_root)
+                .unwrap()
+                .to_string(),
+            "file:///site/public/about/"
+        );
+        assert_eq!(
+            fix_local_url("intro.md", &page, &site_root)
+                .unwrap()
+                .to_string(),
+            "file:///site/public/docs/intro.md"
+        );
+        assert_eq!(
+            fix_local_url("//test.com/a", &page, &site_root)
+                .unwrap()
+                .to_string(),
+            "https://test.com/a"
+        );
+    }
+
+    #[test]
+    fn test_host_matches() {
+        assert!(host_matches("example.com", "example.com"));
+        assert!(host_matches("docs.example.com", "example.com"));
+        assert!(host_matches("anything.org", "*"));
+        assert!(!host_matches("badexample.com", "example.com"));
+        assert!(!host_matches("example.com.evil.org", "example.com"));
     }
 }
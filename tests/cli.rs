@@ -0,0 +1,23 @@
+use std::{fs, process::Command};
+
+#[test]
+fn test_check_json_output_is_only_the_report() {
+    let dir = tempfile::tempdir().unwrap();
+    let site = dir.path();
+    fs::write(
+        site.join("index.html"),
+        r#"<a href="other.html">ok</a> <a href="missing.html">gone</a>
+        <a href="htp://typo">typo</a> <a href="tel:123">call</a>"#,
+    )
+    .unwrap();
+    fs::write(site.join("other.html"), "<p>hi</p>").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rlinks"))
+        .args(["check", site.to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["links"].as_array().unwrap().len(), 2);
+    assert!(!output.stderr.is_empty());
+}
@@ -0,0 +1,133 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    net::TcpListener,
+    path::Path,
+    process::Command,
+    thread,
+};
+
+use openssl::{
+    asn1::Asn1Time,
+    bn::BigNum,
+    hash::MessageDigest,
+    pkey::{PKey, Private},
+    rsa::Rsa,
+    ssl::{SslAcceptor, SslMethod},
+    x509::{
+        extension::{BasicConstraints, SubjectAlternativeName},
+        X509Builder, X509NameBuilder, X509,
+    },
+};
+
+/// A certificate for `name`, signed by `issuer` or by itself. Self-signed ones are authorities
+fn certificate(name: &str, issuer: Option<&(X509, PKey<Private>)>) -> (X509, PKey<Private>) {
+    let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let mut subject = X509NameBuilder::new().unwrap();
+    subject.append_entry_by_text("CN", name).unwrap();
+    let subject = subject.build();
+    let mut builder = X509Builder::new().unwrap();
+    builder.set_version(2).unwrap();
+    let serial = BigNum::from_u32(1).unwrap();
+    builder
+        .set_serial_number(&serial.to_asn1_integer().unwrap())
+        .unwrap();
+    builder.set_subject_name(&subject).unwrap();
+    builder
+        .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    builder
+        .set_not_after(&Asn1Time::days_from_now(30).unwrap())
+        .unwrap();
+    builder.set_pubkey(&key).unwrap();
+    match issuer {
+        Some((issuer_cert, issuer_key)) => {
+            builder.set_issuer_name(issuer_cert.subject_name()).unwrap();
+            let san = SubjectAlternativeName::new()
+                .ip("127.0.0.1")
+                .build(&builder.x509v3_context(Some(issuer_cert), None))
+                .unwrap();
+            builder.append_extension(san).unwrap();
+            builder.sign(issuer_key, MessageDigest::sha256()).unwrap();
+        }
+        None => {
+            builder.set_issuer_name(&subject).unwrap();
+            builder
+                .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+                .unwrap();
+            builder.sign(&key, MessageDigest::sha256()).unwrap();
+        }
+    }
+    (builder.build(), key)
+}
+
+/// Serves an empty page over https on a free port, with a certificate signed by `authority`
+fn serve(authority: &(X509, PKey<Private>)) -> u16 {
+    let (cert, key) = certificate("127.0.0.1", Some(authority));
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor.set_private_key(&key).unwrap();
+    acceptor.set_certificate(&cert).unwrap();
+    let acceptor = acceptor.build();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Ok(mut stream) = acceptor.accept(stream) {
+                let _ = stream.read(&mut [0; 4096]);
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                );
+            }
+        }
+    });
+    port
+}
+
+/// Checks a page linking to `port` as if `system` were the system bundle, and returns the status
+/// of the link
+fn check(dir: &Path, port: u16, system: &Path, ca_cert: Option<&Path>) -> u64 {
+    let site = dir.join(format!("site{}", port));
+    fs::create_dir_all(&site).unwrap();
+    fs::write(
+        site.join("index.html"),
+        format!(r#"<a href="https://127.0.0.1:{}/">secure</a>"#, port),
+    )
+    .unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_rlinks"));
+    command
+        .args(["check", site.to_str().unwrap(), "--format", "json"])
+        .env("SSL_CERT_FILE", system);
+    if let Some(ca_cert) = ca_cert {
+        command.arg("--ca-cert").arg(ca_cert);
+    }
+    let output = command.output().unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    report["links"][0]["status"].as_u64().unwrap()
+}
+
+#[test]
+fn test_ca_cert_adds_to_the_system_bundle() {
+    let dir = tempfile::tempdir().unwrap();
+    let write_authority = |name: &str| {
+        let authority = certificate(name, None);
+        let path = dir.path().join(format!("{}.pem", name));
+        fs::write(&path, authority.0.to_pem().unwrap()).unwrap();
+        (authority, path)
+    };
+    let (system, system_pem) = write_authority("system");
+    let (internal, internal_pem) = write_authority("internal");
+
+    let public_site = serve(&system);
+    assert_eq!(check(dir.path(), public_site, &system_pem, None), 200);
+    assert_eq!(
+        check(dir.path(), public_site, &system_pem, Some(&internal_pem)),
+        200
+    );
+
+    let internal_site = serve(&internal);
+    assert_eq!(check(dir.path(), internal_site, &system_pem, None), 495);
+    assert_eq!(
+        check(dir.path(), internal_site, &system_pem, Some(&internal_pem)),
+        200
+    );
+}